@@ -0,0 +1,203 @@
+//! Baselines record known diagnostics, so that only new ones are reported.
+//!
+//! Diagnostics are matched by file, lint code and a fingerprint of the
+//! offending text, rather than by position, so that edits elsewhere in
+//! a file do not invalidate the baseline. Each entry hides at most one
+//! diagnostic, a second copy of a known diagnostic is reported as new.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{err::BaselineErr, lint::LintResult, utils};
+
+use serde::{Deserialize, Serialize};
+use vfs::ReadOnlyVfs;
+
+const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Baseline {
+    version: u32,
+    entries: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Entry {
+    file: PathBuf,
+    code: u32,
+    fingerprint: String,
+    /// Not used for matching, but makes the baseline easier to review
+    #[serde(default)]
+    message: String,
+}
+
+impl Baseline {
+    /// A baseline of every diagnostic in `results`
+    pub fn from_results(results: &[LintResult], vfs: &ReadOnlyVfs) -> Self {
+        let mut entries = results
+            .iter()
+            .flat_map(|result| {
+                let file = normalize(vfs.file_path(result.file_id));
+                let src = vfs.get_str(result.file_id);
+                result.reports.iter().flat_map(move |report| {
+                    let file = file.clone();
+                    report.diagnostics.iter().map(move |diagnostic| Entry {
+                        file: file.clone(),
+                        code: report.code,
+                        fingerprint: fingerprint(&src[diagnostic.at]),
+                        message: diagnostic.message.clone(),
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        // sorted, so that baselines diff well
+        entries.sort();
+        Self {
+            version: VERSION,
+            entries,
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, BaselineErr> {
+        let path = path.as_ref();
+        let contents =
+            fs::read_to_string(path).map_err(|e| BaselineErr::InvalidPath(path.to_owned(), e))?;
+        let baseline: Self =
+            serde_json::from_str(&contents).map_err(|e| BaselineErr::Parse(path.to_owned(), e))?;
+        if baseline.version != VERSION {
+            return Err(BaselineErr::UnsupportedVersion(baseline.version));
+        }
+        Ok(baseline)
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), BaselineErr> {
+        let path = path.as_ref();
+        let mut contents = serde_json::to_string_pretty(self)
+            .map_err(|e| BaselineErr::Parse(path.to_owned(), e))?;
+        contents.push('\n');
+        fs::write(path, contents).map_err(|e| BaselineErr::InvalidPath(path.to_owned(), e))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove diagnostics found in the baseline from the result of
+    /// linting a single file, reports left without diagnostics are
+    /// dropped
+    pub fn filter(&self, mut result: LintResult, file_path: &Path, src: &str) -> LintResult {
+        let file = normalize(file_path);
+        let mut known = HashMap::new();
+        for entry in self.entries.iter().filter(|e| e.file == file) {
+            *known
+                .entry((entry.code, entry.fingerprint.clone()))
+                .or_insert(0) += 1;
+        }
+        if known.is_empty() {
+            return result;
+        }
+
+        for report in result.reports.iter_mut() {
+            let code = report.code;
+            report.diagnostics.retain(|diagnostic| {
+                let fingerprint = fingerprint(&src[diagnostic.at]);
+                match known.get_mut(&(code, fingerprint)) {
+                    Some(count) if *count > 0 => {
+                        *count -= 1;
+                        false
+                    }
+                    _ => true,
+                }
+            });
+        }
+        result
+            .reports
+            .retain(|report| !report.diagnostics.is_empty());
+        result
+    }
+}
+
+// `statix check`, `statix check .` and `statix check ./` should all
+// produce the same paths
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+// reindenting or reflowing code does not change its fingerprint
+fn fingerprint(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("{:016x}", utils::hash(text.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint;
+
+    use lib::session::SessionInfo;
+
+    fn results(path: &str, src: &str) -> (ReadOnlyVfs, Vec<LintResult>) {
+        let vfs = ReadOnlyVfs::singleton(path, src.as_bytes());
+        let session = SessionInfo::from_version("2.4".parse().unwrap());
+        let results = vfs
+            .iter()
+            .map(|entry| lint::lint(entry, &session))
+            .collect();
+        (vfs, results)
+    }
+
+    fn baseline(path: &str, src: &str) -> Baseline {
+        let (vfs, results) = results(path, src);
+        Baseline::from_results(&results, &vfs)
+    }
+
+    // codes of the diagnostics of `src` that are not in `baseline`
+    fn new(baseline: &Baseline, path: &str, src: &str) -> Vec<u32> {
+        let (_, results) = results(path, src);
+        results
+            .into_iter()
+            .flat_map(|result| baseline.filter(result, Path::new(path), src).reports)
+            .flat_map(|report| vec![report.code; report.diagnostics.len()])
+            .collect()
+    }
+
+    #[test]
+    fn line_shifts_and_reindentation() {
+        let known = baseline("./default.nix", "{\n  a = x: f x;\n}\n");
+        assert_eq!(known.len(), 1);
+
+        let moved = "# moved down\n\n{\n  b = 1;\n  a =\n    x:\n      f   x;\n}\n";
+        assert!(new(&known, "default.nix", moved).is_empty());
+        // the same diagnostic in another file is new
+        assert_eq!(new(&known, "other.nix", moved), vec![7]);
+    }
+
+    #[test]
+    fn each_entry_hides_one_diagnostic() {
+        let known = baseline("default.nix", "[ { a = a; } { a = a; } ]\n");
+        assert_eq!(known.len(), 2);
+
+        assert!(new(&known, "default.nix", "[ { a = a; } ]\n").is_empty());
+        assert!(new(&known, "default.nix", "[ { a = a; } { a = a; } ]\n").is_empty());
+        // a third copy is new
+        assert_eq!(
+            new(
+                &known,
+                "default.nix",
+                "[ { a = a; } { a = a; } { a = a; } ]\n"
+            ),
+            vec![3]
+        );
+        // as is a diagnostic of other text
+        assert_eq!(new(&known, "default.nix", "[ { b = b; } ]\n"), vec![3]);
+    }
+}
@@ -0,0 +1,387 @@
+//! On-disk cache of lint results.
+//!
+//! Entries live under `$XDG_CACHE_HOME/statix` and are keyed by a
+//! SHA-256 digest of the contents of a file, along with everything else that decides which
+//! reports are produced: the enabled lints, their severities and
+//! options, the nix version and the version of statix itself. Entries
+//! are never invalidated, a change to any of the above simply misses
+//! the cache. Entries of each config are kept in a directory of their
+//! own, within a directory for the version of statix, so that those
+//! not used for a while can be pruned as a whole.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime},
+};
+
+use crate::{config::LintSeverity, LintMap};
+
+use lib::{session::SessionInfo, Diagnostic, Edit, Report, Suggestion, LINTS};
+use rnix::{TextRange, TextSize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Results of configs that were not used for this long are pruned
+const MAX_UNUSED: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Written to the directory of a config whenever a run uses it
+const USED: &str = "used";
+
+pub struct Cache {
+    // the directory of the config
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// The cache for results produced by `lints` under `session`, `None`
+    /// if there is nowhere to keep it
+    pub fn new(lints: &LintMap, session: &SessionInfo) -> Option<Self> {
+        let config = format!(
+            "{:?} {} {:?}",
+            session.version(),
+            lints.fingerprint(),
+            session.options()
+        );
+        let dir = dir()?
+            .join(env!("CARGO_PKG_VERSION"))
+            .join(digest(config.as_bytes()));
+        mark_used(&dir);
+        Some(Self { dir })
+    }
+
+    fn path(&self, contents: &str) -> PathBuf {
+        let key = digest(contents.as_bytes());
+        self.dir.join(&key[..2]).join(format!("{}.toml", &key[2..]))
+    }
+
+    /// Cached reports for a file with these contents, unreadable
+    /// entries count as misses
+    pub fn get(&self, contents: &str) -> Option<Vec<Report>> {
+        let entry = fs::read_to_string(self.path(contents)).ok()?;
+        let entry: Entry = toml::de::from_str(&entry).ok()?;
+        entry
+            .reports
+            .into_iter()
+            .map(CachedReport::into_report)
+            .collect()
+    }
+
+    /// Store reports for a file with these contents. The cache is only
+    /// an optimization, failing to write to it is not an error.
+    pub fn insert(&self, contents: &str, reports: &[Report]) {
+        let entry = Entry {
+            reports: reports.iter().map(CachedReport::from_report).collect(),
+        };
+        if let Ok(entry) = toml::ser::to_string(&entry) {
+            let _ = write_atomic(&self.path(contents), &entry);
+        }
+    }
+}
+
+// hex encoded, unlike `utils::hash` it is safe to take a match for
+// equal contents
+fn digest(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// `$XDG_CACHE_HOME/statix`, or `~/.cache/statix`
+pub fn dir() -> Option<PathBuf> {
+    let absolute = |var| {
+        env::var_os(var)
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+    };
+    absolute("XDG_CACHE_HOME")
+        .or_else(|| absolute("HOME").map(|home| home.join(".cache")))
+        .map(|cache| cache.join("statix"))
+}
+
+/// Remove the results of other versions of statix, and of configs that
+/// were not used for a while. Configs used by this run are always kept.
+/// The cache is only an optimization, failing to prune it is not an
+/// error.
+pub fn prune() {
+    if let (Some(dir), Some(since)) = (dir(), SystemTime::now().checked_sub(MAX_UNUSED)) {
+        let marked = MARKED.lock().unwrap();
+        prune_dir(&dir, env!("CARGO_PKG_VERSION"), since, &marked);
+    }
+}
+
+fn prune_dir(dir: &Path, version: &str, since: SystemTime, keep: &[PathBuf]) {
+    let entries = |dir: &Path| {
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>()
+    };
+    let current = dir.join(version);
+    for path in entries(dir).into_iter().filter(|p| *p != current) {
+        let _ = fs::remove_dir_all(&path).or_else(|_| fs::remove_file(&path));
+    }
+    for config in entries(&current) {
+        if keep.contains(&config) {
+            continue;
+        }
+        // a config without a marker may be in the middle of being
+        // created, the directory itself is as recent
+        let used = fs::metadata(config.join(USED))
+            .or_else(|_| fs::metadata(&config))
+            .and_then(|m| m.modified());
+        if matches!(used, Ok(used) if used < since) {
+            let _ = fs::remove_dir_all(config);
+        }
+    }
+}
+
+// configs used by this run
+static MARKED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+// the time a config was last used, the marker is written once per run
+// rather than once per file
+fn mark_used(dir: &Path) {
+    let mut marked = MARKED.lock().unwrap();
+    if !marked.iter().any(|d| d == dir) {
+        let _ = write_atomic(&dir.join(USED), "");
+        marked.push(dir.to_owned());
+    }
+}
+
+// files are linted in parallel, and several files may share contents,
+// entries are written elsewhere first so that readers never see half
+// of an entry
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let parent = path.parent().unwrap();
+    fs::create_dir_all(parent)?;
+    let tmp = parent.join(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    reports: Vec<CachedReport>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedReport {
+    code: u32,
+    severity: LintSeverity,
+    diagnostics: Vec<CachedDiagnostic>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedDiagnostic {
+    at: (u32, u32),
+    message: String,
+    suggestion: Option<CachedSuggestion>,
+}
+
+// values come before tables in toml, fields are ordered to match
+#[derive(Serialize, Deserialize)]
+struct CachedSuggestion {
+    applicability: String,
+    edits: Vec<CachedEdit>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedEdit {
+    at: (u32, u32),
+    fix: String,
+}
+
+fn to_range((start, end): (u32, u32)) -> Option<TextRange> {
+    if start <= end {
+        Some(TextRange::new(TextSize::from(start), TextSize::from(end)))
+    } else {
+        None
+    }
+}
+
+fn from_range(at: TextRange) -> (u32, u32) {
+    (at.start().into(), at.end().into())
+}
+
+impl CachedReport {
+    fn from_report(report: &Report) -> Self {
+        Self {
+            code: report.code,
+            severity: report.severity.into(),
+            diagnostics: report
+                .diagnostics
+                .iter()
+                .map(|d| CachedDiagnostic {
+                    at: from_range(d.at),
+                    message: d.message.clone(),
+                    suggestion: d.suggestion.as_ref().map(|s| CachedSuggestion {
+                        edits: s
+                            .edits
+                            .iter()
+                            .map(|e| CachedEdit {
+                                at: from_range(e.at),
+                                fix: e.fix.to_string(),
+                            })
+                            .collect(),
+                        applicability: s.applicability.as_str().to_owned(),
+                    }),
+                })
+                .collect(),
+        }
+    }
+
+    fn into_report(self) -> Option<Report> {
+        let note = match self.code {
+            0 => "Syntax error",
+            code => LINTS.iter().find(|l| l.code() == code)?.note(),
+        };
+        let mut report = Report::new(note, self.code).severity(self.severity.into());
+        for d in self.diagnostics {
+            let at = to_range(d.at)?;
+            let diagnostic = match d.suggestion {
+                // only the text of a fix is ever needed, parsing it
+                // again is good enough
+                Some(s) if !s.edits.is_empty() => {
+                    let edits = s
+                        .edits
+                        .into_iter()
+                        .map(|e| Some(Edit::new(to_range(e.at)?, rnix::parse(&e.fix).node())))
+                        .collect::<Option<Vec<_>>>()?;
+                    let applicability = s.applicability.parse().ok()?;
+                    let suggestion = Suggestion::with_edits(edits).applicability(applicability);
+                    Diagnostic::suggest(at, d.message, suggestion)
+                }
+                Some(_) => return None,
+                None => Diagnostic::new(at, d.message),
+            };
+            report.diagnostics.push(diagnostic);
+        }
+        Some(report)
+    }
+}
+
+pub mod main {
+    use crate::{
+        config::{Cache as CacheConfig, CacheCmd},
+        err::{CacheErr, StatixErr},
+    };
+
+    pub fn main(cache_config: CacheConfig) -> Result<(), StatixErr> {
+        match cache_config.cmd {
+            CacheCmd::Clean => {
+                let dir = super::dir().ok_or(CacheErr::NoCacheDir)?;
+                if dir.exists() {
+                    std::fs::remove_dir_all(&dir).map_err(CacheErr::from)?;
+                    println!("Removed {}", dir.display());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let src = "{ a = a; }";
+        let reports = crate::lint::lint(
+            vfs::VfsEntry {
+                file_id: vfs::FileId(0),
+                file_path: Path::new("a.nix"),
+                contents: src,
+            },
+            &SessionInfo::from_version("2.4".parse().unwrap()),
+        )
+        .reports;
+        assert!(!reports.is_empty());
+
+        let entry = Entry {
+            reports: reports.iter().map(CachedReport::from_report).collect(),
+        };
+        let entry: Entry = toml::de::from_str(&toml::ser::to_string(&entry).unwrap()).unwrap();
+        let cached = entry
+            .reports
+            .into_iter()
+            .map(CachedReport::into_report)
+            .collect::<Option<Vec<_>>>()
+            .unwrap();
+        assert_eq!(cached.len(), reports.len());
+        for (cached, report) in cached.iter().zip(reports.iter()) {
+            assert_eq!(cached.note, report.note);
+            assert_eq!(cached.code, report.code);
+            assert_eq!(cached.severity, report.severity);
+            let mut fixed = src.to_owned();
+            let mut cached_fixed = src.to_owned();
+            report.apply(&mut fixed);
+            cached.apply(&mut cached_fixed);
+            assert_eq!(fixed, cached_fixed);
+        }
+    }
+
+    #[test]
+    fn prune() {
+        let recent = (0..10)
+            .map(|i| format!("0.5.8/recent{}/used", i))
+            .collect::<Vec<_>>();
+        let mut files = vec![
+            ("0.5.7/aaaa/00/entry.toml", ""),
+            ("0.5.8/old/used", ""),
+            ("0.5.8/old_in_use/used", ""),
+            ("0.5.8/unmarked/00/entry.toml", ""),
+            ("00/entry.toml", ""),
+        ];
+        files.extend(recent.iter().map(|path| (path.as_str(), "")));
+        let root = crate::utils::tree("cache", &files);
+        let unused = |config: &str| {
+            let marker = fs::File::options()
+                .write(true)
+                .open(root.join("0.5.8").join(config).join(USED))
+                .unwrap();
+            marker.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+        };
+        unused("old");
+        unused("old_in_use");
+
+        let since = SystemTime::now() - MAX_UNUSED;
+        prune_dir(&root, "0.5.8", since, &[root.join("0.5.8/old_in_use")]);
+        let mut left = fs::read_dir(&root)
+            .unwrap()
+            .chain(fs::read_dir(root.join("0.5.8")).unwrap())
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        left.sort_unstable();
+        let mut expected = vec!["0.5.8", "old_in_use", "unmarked"];
+        let recent = (0..10).map(|i| format!("recent{}", i)).collect::<Vec<_>>();
+        expected.extend(recent.iter().map(String::as_str));
+        expected.sort_unstable();
+        assert_eq!(left, expected);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn digest() {
+        assert_eq!(
+            super::digest(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
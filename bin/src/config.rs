@@ -1,15 +1,29 @@
 use std::{
+    collections::BTreeMap,
     default::Default,
-    fmt, fs,
+    env, fmt, fs,
     path::{Path, PathBuf},
     str::FromStr,
 };
 
-use crate::{dirs, err::ConfigErr, utils, LintMap};
+#[cfg(feature = "json")]
+use crate::{baseline::Baseline, err::BaselineErr};
+use crate::{
+    cache,
+    dirs::{self, Ignore},
+    err::ConfigErr,
+    profile::{Profile, Profiles},
+    utils, LintMap,
+};
 
 use clap::Parser;
-use lib::{session::Version, LINTS};
-use serde::{Deserialize, Serialize};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use lib::{
+    session::Version, suppression, Applicability, Category, Lint, LintOptions, OptionValue,
+    Severity, LINTS,
+};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use toml::Spanned;
 use vfs::ReadOnlyVfs;
 
 #[derive(Parser, Debug)]
@@ -22,6 +36,9 @@ pub struct Opts {
 #[derive(Parser, Debug)]
 pub enum SubCommand {
     /// Lints and suggestions for the nix programming language
+    #[clap(
+        after_help = "Exits with status 2 if errors were found, syntax errors included, 1 if warnings were found, and 0 if only hints were found."
+    )]
     Check(Check),
     /// Find and fix issues raised by statix-check
     Fix(Fix),
@@ -33,6 +50,13 @@ pub enum SubCommand {
     Dump(Dump),
     /// List all available lints
     List(List),
+    /// Manage the cache of lint results
+    Cache(Cache),
+    /// Inspect config files
+    Config(Config),
+    /// Start a language server, communicating over stdin and stdout
+    #[cfg(feature = "lsp")]
+    Lsp(Lsp),
 }
 
 #[derive(Parser, Debug)]
@@ -49,23 +73,110 @@ pub struct Check {
     #[clap(short, long)]
     unrestricted: bool,
 
-    /// Output format.
-    #[cfg_attr(feature = "json", doc = "Supported values: stderr, errfmt, json")]
-    #[cfg_attr(not(feature = "json"), doc = "Supported values: stderr, errfmt")]
-    #[clap(short = 'o', long, default_value_t, parse(try_from_str))]
-    pub format: OutFormat,
+    /// Output format, defaults to github within GitHub Actions and to stderr elsewhere.
+    #[cfg_attr(
+        feature = "json",
+        doc = "Supported values: stderr, errfmt, checkstyle, junit, github, json, sarif"
+    )]
+    #[cfg_attr(
+        not(feature = "json"),
+        doc = "Supported values: stderr, errfmt, checkstyle, junit, github"
+    )]
+    #[clap(short = 'o', long, parse(try_from_str))]
+    pub format: Option<OutFormat>,
 
-    /// Path to statix.toml or its parent directory
-    #[clap(short = 'c', long = "config", default_value = ".")]
-    pub conf_path: PathBuf,
+    /// Path to statix.toml or its parent directory, its config is used
+    /// for every file. Without it, each file is checked with the
+    /// statix.toml nearest to it.
+    #[clap(short = 'c', long = "config")]
+    pub conf_path: Option<PathBuf>,
 
     /// Enable "streaming" mode, accept file on stdin, output diagnostics on stdout
     #[clap(short, long = "stdin")]
     pub streaming: bool,
+
+    /// Keep running, and run again on files as they change
+    #[clap(short, long, conflicts_with = "streaming")]
+    pub watch: bool,
+
+    /// Neither read from nor write to the cache of lint results
+    #[clap(long)]
+    pub no_cache: bool,
+
+    /// Enable only these lints, given as categories, lint names, globs of lint names, or codes
+    #[clap(
+        long,
+        multiple_occurrences = true,
+        use_delimiter = true,
+        number_of_values = 1
+    )]
+    pub select: Vec<String>,
+
+    /// Disable these lints, given as categories, lint names, globs of lint names, or codes
+    #[clap(
+        long,
+        multiple_occurrences = true,
+        use_delimiter = true,
+        number_of_values = 1
+    )]
+    pub ignore_lint: Vec<String>,
+
+    /// Hide diagnostics recorded in this baseline file, only new ones are reported
+    #[cfg(feature = "json")]
+    #[clap(long, parse(from_os_str))]
+    pub baseline: Option<PathBuf>,
+
+    /// Record current diagnostics in this baseline file instead of reporting them
+    #[cfg(feature = "json")]
+    #[clap(long, parse(from_os_str), conflicts_with_all = &["baseline", "watch"])]
+    pub write_baseline: Option<PathBuf>,
 }
 
 impl Check {
-    pub fn vfs(&self, extra_ignores: &[String]) -> Result<ReadOnlyVfs, ConfigErr> {
+    pub fn format(&self) -> OutFormat {
+        self.format.unwrap_or_else(|| {
+            if env::var("GITHUB_ACTIONS").as_deref() == Ok("true") {
+                OutFormat::Github
+            } else {
+                OutFormat::default()
+            }
+        })
+    }
+
+    /// The baseline to compare against, if any
+    #[cfg(feature = "json")]
+    pub fn baseline(&self) -> Result<Option<Baseline>, BaselineErr> {
+        self.baseline.as_ref().map(Baseline::from_path).transpose()
+    }
+
+    /// Profiles of the files to check, lints are narrowed down by
+    /// `--select` and `--ignore-lint`
+    pub fn profiles(&self, conf_file: ConfFile) -> Result<Profiles, ConfigErr> {
+        profiles(
+            self.conf_path.is_some(),
+            conf_file,
+            &self.select,
+            &self.ignore_lint,
+        )
+    }
+
+    /// The cache of lint results, unless disabled
+    pub fn cache<'p>(&self, profile: &'p Profile) -> Option<&'p cache::Cache> {
+        if self.no_cache {
+            None
+        } else {
+            profile.cache()
+        }
+    }
+
+    /// Prune the cache of lint results, unless disabled
+    pub fn prune_cache(&self) {
+        if !self.no_cache {
+            cache::prune();
+        }
+    }
+
+    pub fn vfs(&self, ignore: &Ignore) -> Result<ReadOnlyVfs, ConfigErr> {
         if self.streaming {
             use std::io::{self, BufRead};
             let src = io::stdin()
@@ -76,12 +187,26 @@ impl Check {
                 .join("\n");
             Ok(ReadOnlyVfs::singleton("<stdin>", src.as_bytes()))
         } else {
-            let all_ignores = [self.ignore.as_slice(), extra_ignores].concat();
-            let ignore = dirs::build_ignore_set(&all_ignores, &self.target, self.unrestricted)?;
             let files = dirs::walk_nix_files(ignore, &self.target)?;
             vfs(files.collect::<Vec<_>>())
         }
     }
+
+    /// Files to skip, by `.gitignore`, `--ignore`, and the `ignore`
+    /// lists of `profiles`
+    pub fn ignore<'a>(&self, profiles: &'a Profiles) -> Result<Ignore<'a>, ConfigErr> {
+        let gitignore = dirs::build_ignore_set(&self.ignore, &self.target, self.unrestricted)?;
+        Ok(Ignore::new(gitignore, profiles))
+    }
+
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// The config file, or the directory to look for it from
+    pub fn conf_path(&self) -> &Path {
+        conf_path(&self.conf_path)
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -102,13 +227,45 @@ pub struct Fix {
     #[clap(short, long = "dry-run")]
     pub diff_only: bool,
 
-    /// Path to statix.toml or its parent directory
-    #[clap(short = 'c', long = "config", default_value = ".")]
-    pub conf_path: PathBuf,
+    /// Path to statix.toml or its parent directory, its config is used
+    /// for every file. Without it, each file is checked with the
+    /// statix.toml nearest to it.
+    #[clap(short = 'c', long = "config")]
+    pub conf_path: Option<PathBuf>,
 
     /// Enable "streaming" mode, accept file on stdin, output diagnostics on stdout
     #[clap(short, long = "stdin")]
     pub streaming: bool,
+
+    /// Keep running, and run again on files as they change
+    #[clap(short, long, conflicts_with = "streaming")]
+    pub watch: bool,
+
+    /// Give up on a file if fixing it takes more passes than this
+    #[clap(long, default_value = "64")]
+    pub max_passes: usize,
+
+    /// Also apply fixes that may change the meaning of code
+    #[clap(long)]
+    pub unsafe_fixes: bool,
+
+    /// Enable only these lints, given as categories, lint names, globs of lint names, or codes
+    #[clap(
+        long,
+        multiple_occurrences = true,
+        use_delimiter = true,
+        number_of_values = 1
+    )]
+    pub select: Vec<String>,
+
+    /// Disable these lints, given as categories, lint names, globs of lint names, or codes
+    #[clap(
+        long,
+        multiple_occurrences = true,
+        use_delimiter = true,
+        number_of_values = 1
+    )]
+    pub ignore_lint: Vec<String>,
 }
 
 pub enum FixOut {
@@ -118,7 +275,7 @@ pub enum FixOut {
 }
 
 impl Fix {
-    pub fn vfs(&self, extra_ignores: &[String]) -> Result<ReadOnlyVfs, ConfigErr> {
+    pub fn vfs(&self, ignore: &Ignore) -> Result<ReadOnlyVfs, ConfigErr> {
         if self.streaming {
             use std::io::{self, BufRead};
             let src = io::stdin()
@@ -129,13 +286,47 @@ impl Fix {
                 .join("\n");
             Ok(ReadOnlyVfs::singleton("<stdin>", src.as_bytes()))
         } else {
-            let all_ignores = [self.ignore.as_slice(), extra_ignores].concat();
-            let ignore = dirs::build_ignore_set(&all_ignores, &self.target, self.unrestricted)?;
             let files = dirs::walk_nix_files(ignore, &self.target)?;
             vfs(files.collect::<Vec<_>>())
         }
     }
 
+    /// Files to skip, by `.gitignore`, `--ignore`, and the `ignore`
+    /// lists of `profiles`
+    pub fn ignore<'a>(&self, profiles: &'a Profiles) -> Result<Ignore<'a>, ConfigErr> {
+        let gitignore = dirs::build_ignore_set(&self.ignore, &self.target, self.unrestricted)?;
+        Ok(Ignore::new(gitignore, profiles))
+    }
+
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// The config file, or the directory to look for it from
+    pub fn conf_path(&self) -> &Path {
+        conf_path(&self.conf_path)
+    }
+
+    /// The least safe fixes to apply
+    pub fn applicability(&self) -> Applicability {
+        if self.unsafe_fixes {
+            Applicability::Unsafe
+        } else {
+            Applicability::Safe
+        }
+    }
+
+    /// Profiles of the files to check, lints are narrowed down by
+    /// `--select` and `--ignore-lint`
+    pub fn profiles(&self, conf_file: ConfFile) -> Result<Profiles, ConfigErr> {
+        profiles(
+            self.conf_path.is_some(),
+            conf_file,
+            &self.select,
+            &self.ignore_lint,
+        )
+    }
+
     // i need this ugly helper because clap's data model
     // does not reflect what i have in mind
     pub fn out(&self) -> FixOut {
@@ -155,9 +346,23 @@ pub struct Single {
     #[clap(parse(from_os_str))]
     pub target: Option<PathBuf>,
 
-    /// Position to attempt a fix at
-    #[clap(short, long, parse(try_from_str = parse_line_col))]
-    pub position: (usize, usize),
+    /// Position to attempt a fix at, as `line,col`, or a selection,
+    /// as `line,col-line,col`
+    #[clap(short, long, parse(try_from_str))]
+    pub position: Selection,
+
+    /// Only consider fixes of the lint with this code
+    #[clap(long, parse(try_from_str = parse_warning_code))]
+    pub code: Option<u32>,
+
+    /// List the fixes available at the position instead of applying one
+    #[clap(long, conflicts_with = "diff-only")]
+    pub list: bool,
+
+    /// List fixes as JSON
+    #[cfg(feature = "json")]
+    #[clap(long, requires = "list")]
+    pub json: bool,
 
     /// Do not fix files in place, display a diff instead
     #[clap(short, long = "dry-run")]
@@ -167,9 +372,15 @@ pub struct Single {
     #[clap(short, long = "stdin")]
     pub streaming: bool,
 
-    /// Path to statix.toml or its parent directory
-    #[clap(short = 'c', long = "config", default_value = ".")]
-    pub conf_path: PathBuf,
+    /// Path to statix.toml or its parent directory, its config is used
+    /// for every file. Without it, each file is checked with the
+    /// statix.toml nearest to it.
+    #[clap(short = 'c', long = "config")]
+    pub conf_path: Option<PathBuf>,
+
+    /// Also apply fixes that may change the meaning of code
+    #[clap(long)]
+    pub unsafe_fixes: bool,
 }
 
 impl Single {
@@ -189,6 +400,21 @@ impl Single {
             Ok(ReadOnlyVfs::singleton("<stdin>", src.as_bytes()))
         }
     }
+    /// The least safe fixes to apply
+    pub fn applicability(&self) -> Applicability {
+        if self.unsafe_fixes {
+            Applicability::Unsafe
+        } else {
+            Applicability::Safe
+        }
+    }
+    pub fn profiles(&self, conf_file: ConfFile) -> Result<Profiles, ConfigErr> {
+        profiles(self.conf_path.is_some(), conf_file, &[], &[])
+    }
+    /// The config file, or the directory to look for it from
+    pub fn conf_path(&self) -> &Path {
+        conf_path(&self.conf_path)
+    }
     pub fn out(&self) -> FixOut {
         if self.diff_only {
             FixOut::Diff
@@ -200,6 +426,31 @@ impl Single {
     }
 }
 
+/// A position or a range in a file, lines start at 1 and columns
+/// at 0. A position is an empty range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+impl FromStr for Selection {
+    type Err = ConfigErr;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src.split_once('-') {
+            Some((start, end)) => Ok(Self {
+                start: parse_line_col(start)?,
+                end: parse_line_col(end)?,
+            }),
+            None => {
+                let start = parse_line_col(src)?;
+                Ok(Self { start, end: start })
+            }
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 pub struct Explain {
     /// Warning code to explain
@@ -213,11 +464,88 @@ pub struct Dump {}
 #[derive(Parser, Debug)]
 pub struct List {}
 
+#[derive(Parser, Debug)]
+pub struct Cache {
+    #[clap(subcommand)]
+    pub cmd: CacheCmd,
+}
+
+#[derive(Parser, Debug)]
+pub enum CacheCmd {
+    /// Remove all cached lint results
+    Clean,
+}
+
+#[derive(Parser, Debug)]
+pub struct Config {
+    #[clap(subcommand)]
+    pub cmd: ConfigCmd,
+}
+
+#[derive(Parser, Debug)]
+pub enum ConfigCmd {
+    /// Validate a config file, along with those it inherits from and
+    /// the user config
+    Check {
+        /// Path to statix.toml or its parent directory
+        #[clap(default_value = ".", parse(from_os_str))]
+        conf_path: PathBuf,
+    },
+    /// Print the config that applies to a file, along with where each
+    /// setting comes from
+    Show(Show),
+}
+
+#[derive(Parser, Debug)]
+pub struct Show {
+    /// File or directory to show the config of
+    #[clap(default_value = ".", parse(from_os_str))]
+    pub target: PathBuf,
+
+    /// Path to statix.toml or its parent directory, its config is used
+    /// for every file. Without it, each file is checked with the
+    /// statix.toml nearest to it.
+    #[clap(short = 'c', long = "config")]
+    pub conf_path: Option<PathBuf>,
+
+    /// Enable only these lints, given as categories, lint names, globs of lint names, or codes
+    #[clap(
+        long,
+        multiple_occurrences = true,
+        use_delimiter = true,
+        number_of_values = 1
+    )]
+    pub select: Vec<String>,
+
+    /// Disable these lints, given as categories, lint names, globs of lint names, or codes
+    #[clap(
+        long,
+        multiple_occurrences = true,
+        use_delimiter = true,
+        number_of_values = 1
+    )]
+    pub ignore_lint: Vec<String>,
+}
+
+#[cfg(feature = "lsp")]
+#[derive(Parser, Debug)]
+pub struct Lsp {
+    /// Communicate over stdin and stdout, this is the default and only
+    /// transport, the flag is accepted for compatibility with clients
+    #[clap(long)]
+    pub stdio: bool,
+}
+
 #[derive(Debug, Copy, Clone, Default)]
 pub enum OutFormat {
     #[cfg(feature = "json")]
     Json,
+    #[cfg(feature = "json")]
+    Sarif,
     Errfmt,
+    Checkstyle,
+    Junit,
+    Github,
     #[default]
     StdErr,
 }
@@ -230,7 +558,12 @@ impl fmt::Display for OutFormat {
             match self {
                 #[cfg(feature = "json")]
                 Self::Json => "json",
+                #[cfg(feature = "json")]
+                Self::Sarif => "sarif",
                 Self::Errfmt => "errfmt",
+                Self::Checkstyle => "checkstyle",
+                Self::Junit => "junit",
+                Self::Github => "github",
                 Self::StdErr => "stderr",
             }
         )
@@ -246,15 +579,27 @@ impl FromStr for OutFormat {
             "json" => Ok(Self::Json),
             #[cfg(not(feature = "json"))]
             "json" => Err("statix was not compiled with the `json` feature flag"),
+            #[cfg(feature = "json")]
+            "sarif" => Ok(Self::Sarif),
+            #[cfg(not(feature = "json"))]
+            "sarif" => Err("statix was not compiled with the `json` feature flag"),
             "errfmt" => Ok(Self::Errfmt),
+            "checkstyle" => Ok(Self::Checkstyle),
+            "junit" => Ok(Self::Junit),
+            "github" => Ok(Self::Github),
             "stderr" => Ok(Self::StdErr),
-            _ => Err("unknown output format, try: json, errfmt"),
+            _ => Err("unknown output format, try: json, sarif, errfmt, checkstyle, junit, github"),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ConfFile {
+    /// Lints to enable, all lints are enabled if empty
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    enabled: Vec<String>,
+
     #[serde(default = "Vec::new")]
     disabled: Vec<String>,
 
@@ -262,26 +607,438 @@ pub struct ConfFile {
 
     #[serde(default = "Vec::new")]
     pub ignore: Vec<String>,
+
+    #[serde(default)]
+    severity: BTreeMap<String, LintSeverity>,
+
+    /// Options of lints, by lint name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    lints: LintSettings,
+
+    /// Do not inherit settings from config files further up
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    root: bool,
+
+    /// Inherit settings from this config file, rather than from the
+    /// nearest one further up
+    extends: Option<PathBuf>,
+
+    /// Settings for some of the files, see `Override`
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    overrides: Vec<Override>,
+
+    /// Where the config file was read from, globs of overrides are
+    /// relative to its directory
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+/// Settings for the files matched by `files`, they take precedence
+/// over those of the rest of the config file. `enabled` and
+/// `nix_version` replace, `disabled`, `severity` and `lints` add to
+/// them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Override {
+    files: Vec<String>,
+
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    enabled: Vec<String>,
+
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    disabled: Vec<String>,
+
+    nix_version: Option<String>,
+
+    #[serde(default)]
+    severity: BTreeMap<String, LintSeverity>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    lints: LintSettings,
+}
+
+/// Options of lints as written in `statix.toml`, by lint name and then
+/// by option name
+type LintSettings = BTreeMap<String, BTreeMap<String, toml::Value>>;
+
+// the value of an option as lints take it, `None` for values that no
+// option takes
+fn option_value(value: &toml::Value) -> Option<OptionValue> {
+    match value {
+        toml::Value::Boolean(b) => Some(OptionValue::Bool(*b)),
+        toml::Value::Integer(i) => Some(OptionValue::Int(*i)),
+        toml::Value::String(s) => Some(OptionValue::Str(s.clone())),
+        toml::Value::Array(a) => a
+            .iter()
+            .map(|v| v.as_str().map(str::to_owned))
+            .collect::<Option<_>>()
+            .map(OptionValue::List),
+        _ => None,
+    }
+}
+
+fn toml_value(value: &OptionValue) -> toml::Value {
+    match value {
+        OptionValue::Bool(b) => toml::Value::Boolean(*b),
+        OptionValue::Int(i) => toml::Value::Integer(*i),
+        OptionValue::Str(s) => toml::Value::String(s.clone()),
+        OptionValue::List(l) => {
+            toml::Value::Array(l.iter().map(|s| toml::Value::String(s.clone())).collect())
+        }
+    }
+}
+
+impl Override {
+    /// Globs without a slash match file names anywhere, like those of
+    /// a `.gitignore`, other globs match the whole path
+    pub fn matcher(&self) -> Result<GlobSet, ConfigErr> {
+        let mut builder = GlobSetBuilder::new();
+        for file in self.files.iter() {
+            let glob = if file.contains('/') {
+                file.trim_start_matches('/').to_owned()
+            } else {
+                format!("**/{}", file)
+            };
+            let glob = GlobBuilder::new(&glob)
+                .literal_separator(true)
+                .build()
+                .map_err(ConfigErr::OverrideGlob)?;
+            builder.add(glob);
+        }
+        builder.build().map_err(ConfigErr::OverrideGlob)
+    }
+}
+
+const KEYS: [&str; 9] = [
+    "enabled",
+    "disabled",
+    "nix_version",
+    "ignore",
+    "severity",
+    "lints",
+    "root",
+    "extends",
+    "overrides",
+];
+
+const OVERRIDE_KEYS: [&str; 6] = [
+    "files",
+    "enabled",
+    "disabled",
+    "nix_version",
+    "severity",
+    "lints",
+];
+
+type Keys = BTreeMap<Spanned<String>, IgnoredAny>;
+
+#[derive(Deserialize)]
+struct OverrideKeys {
+    #[serde(default)]
+    overrides: Vec<Keys>,
+}
+
+// where lints are named in a config file
+#[derive(Deserialize)]
+struct LintNames {
+    #[serde(default)]
+    enabled: Vec<Spanned<String>>,
+    #[serde(default)]
+    disabled: Vec<Spanned<String>>,
+    #[serde(default)]
+    severity: BTreeMap<Spanned<String>, LintSeverity>,
+    #[serde(default)]
+    lints: BTreeMap<Spanned<String>, BTreeMap<Spanned<String>, Spanned<toml::Value>>>,
+    #[serde(default)]
+    overrides: Vec<LintNames>,
+}
+
+/// A key or lint name of a config file that is not known
+struct Unknown<'a> {
+    at: usize,
+    what: &'static str,
+    name: &'a str,
+    closest: Option<&'static str>,
+}
+
+impl<'a> Unknown<'a> {
+    fn new(
+        name: &'a Spanned<String>,
+        what: &'static str,
+        known: impl Iterator<Item = &'static str>,
+    ) -> Self {
+        Self {
+            at: name.start(),
+            what,
+            name: name.get_ref(),
+            closest: closest(name.get_ref(), known),
+        }
+    }
+}
+
+fn unknown_keys<'a>(keys: &'a Keys, known: &'static [&'static str]) -> Vec<Unknown<'a>> {
+    keys.keys()
+        .filter(|k| !known.contains(&k.get_ref().as_str()))
+        .map(|k| Unknown::new(k, "key", known.iter().copied()))
+        .collect()
+}
+
+// strings of an option that it may not hold, they are pointed at
+// by the start of the value
+fn unknown_values<'a>(
+    value: &'a Spanned<toml::Value>,
+    known: &'static [&'static str],
+) -> Vec<Unknown<'a>> {
+    let strings = match value.get_ref() {
+        toml::Value::String(s) => vec![s.as_str()],
+        toml::Value::Array(a) => a.iter().filter_map(toml::Value::as_str).collect(),
+        _ => vec![],
+    };
+    strings
+        .into_iter()
+        .filter(|s| !known.is_empty() && !known.contains(s))
+        .map(|s| Unknown {
+            at: value.start(),
+            what: "value",
+            name: s,
+            closest: closest(s, known.iter().copied()),
+        })
+        .collect()
+}
+
+impl LintNames {
+    fn unknown(&self) -> Vec<Unknown<'_>> {
+        let lint_names = || LINTS.iter().map(|l| l.name());
+        let selectors = self
+            .enabled
+            .iter()
+            .chain(self.disabled.iter())
+            .filter(|s| !LINTS.iter().any(|l| selects(s.get_ref(), &***l)))
+            .map(|s| {
+                let categories = Category::ALL.iter().map(Category::as_str);
+                Unknown::new(s, "lint", lint_names().chain(categories))
+            });
+        let severities = self
+            .severity
+            .keys()
+            .filter(|s| !lint_names().any(|name| name == s.get_ref()))
+            .map(|s| Unknown::new(s, "lint", lint_names()));
+        let options = self.lints.iter().flat_map(|(name, options)| {
+            match LINTS.iter().find(|l| l.name() == name.get_ref()) {
+                Some(lint) => {
+                    let declared = lint.options();
+                    let known = declared.iter().map(|o| o.name).collect::<Vec<_>>();
+                    let values = options.iter().flat_map(|(o, value)| {
+                        let declared = declared.iter().find(|d| d.name == o.get_ref());
+                        unknown_values(value, declared.map_or(&[], |d| d.values))
+                    });
+                    options
+                        .keys()
+                        .filter(|o| !known.contains(&o.get_ref().as_str()))
+                        .map(|o| Unknown::new(o, "option", known.iter().copied()))
+                        .chain(values)
+                        .collect()
+                }
+                None => vec![Unknown::new(name, "lint", lint_names())],
+            }
+        });
+        selectors
+            .chain(severities)
+            .chain(options)
+            .chain(self.overrides.iter().flat_map(LintNames::unknown))
+            .collect()
+    }
+
+    /// Options set to values of the wrong type, along with the type
+    /// they take. Only meant for names that are known.
+    fn mistyped(&self) -> Vec<(usize, String, &'static str)> {
+        let mut mistyped = Vec::new();
+        for (name, options) in self.lints.iter() {
+            let declared = LINTS
+                .iter()
+                .find(|l| l.name() == name.get_ref())
+                .map(|l| l.options())
+                .unwrap_or_default();
+            for (option, value) in options.iter() {
+                let declared = declared.iter().find(|o| o.name == option.get_ref());
+                if let Some(declared) = declared {
+                    if !option_value(value.get_ref()).is_some_and(|v| (declared.accepts)(&v)) {
+                        let name = format!("{}.{}", name.get_ref(), option.get_ref());
+                        mistyped.push((value.start(), name, declared.ty));
+                    }
+                }
+            }
+        }
+        mistyped.extend(self.overrides.iter().flat_map(LintNames::mistyped));
+        mistyped
+    }
+}
+
+/// The candidate closest to `name`, unless none of them are close
+fn closest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .map(|c| (strsim::levenshtein(name, c), c))
+        .filter(|(distance, _)| *distance <= (name.len() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c)
+}
+
+// lines and columns start at 1
+fn line_col(src: &str, at: usize) -> (usize, usize) {
+    let before = &src[..at];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Severity of a lint as written in `statix.toml`
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Hint,
+    #[serde(alias = "warning")]
+    Warn,
+    Error,
+}
+
+impl LintSeverity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Hint => "hint",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl From<LintSeverity> for Severity {
+    fn from(severity: LintSeverity) -> Self {
+        match severity {
+            LintSeverity::Hint => Severity::Hint,
+            LintSeverity::Warn => Severity::Warn,
+            LintSeverity::Error => Severity::Error,
+        }
+    }
+}
+
+impl From<Severity> for LintSeverity {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Hint => LintSeverity::Hint,
+            Severity::Warn => LintSeverity::Warn,
+            Severity::Error => LintSeverity::Error,
+        }
+    }
 }
 
 impl Default for ConfFile {
     fn default() -> Self {
+        let enabled = Default::default();
         let disabled = Default::default();
         let ignore = Default::default();
         let nix_version = Default::default();
+        let severity = Default::default();
+        let lints = Default::default();
+        let root = Default::default();
+        let extends = Default::default();
+        let overrides = Default::default();
+        let path = Default::default();
         Self {
+            enabled,
             disabled,
             nix_version,
             ignore,
+            severity,
+            lints,
+            root,
+            extends,
+            overrides,
+            path,
         }
     }
 }
 
 impl ConfFile {
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ConfigErr> {
-        let path = path.as_ref();
-        let config_file = fs::read_to_string(path).map_err(ConfigErr::InvalidPath)?;
-        toml::de::from_str(&config_file).map_err(ConfigErr::ConfFileParse)
+        let path = fs::canonicalize(path.as_ref()).map_err(ConfigErr::InvalidPath)?;
+        let config_file = fs::read_to_string(&path).map_err(ConfigErr::InvalidPath)?;
+        let mut conf_file = Self::parse(&config_file, &path)?;
+        conf_file.path = path;
+        Ok(conf_file)
+    }
+    /// Parse a config file, unknown keys and lint names are errors.
+    /// `path` is only used to point at mistakes.
+    pub fn parse(src: &str, path: &Path) -> Result<Self, ConfigErr> {
+        let parse_err = |e| ConfigErr::ConfFileParse(path.to_owned(), e);
+        // mistakes are found before deserializing for real, so that
+        // they can be pointed at
+        let keys: Keys = toml::de::from_str(src).map_err(parse_err)?;
+        let override_keys: OverrideKeys = toml::de::from_str(src).map_err(parse_err)?;
+        let lint_names: LintNames = toml::de::from_str(src).map_err(parse_err)?;
+        let first = unknown_keys(&keys, &KEYS)
+            .into_iter()
+            .chain(
+                override_keys
+                    .overrides
+                    .iter()
+                    .flat_map(|keys| unknown_keys(keys, &OVERRIDE_KEYS)),
+            )
+            .chain(lint_names.unknown())
+            .min_by_key(|u| u.at);
+        if let Some(unknown) = first {
+            let (line, col) = line_col(src, unknown.at);
+            return Err(ConfigErr::Unknown {
+                path: path.to_owned(),
+                line,
+                col,
+                what: unknown.what,
+                name: unknown.name.to_owned(),
+                hint: unknown
+                    .closest
+                    .map(|c| format!(", did you mean `{}`?", c))
+                    .unwrap_or_default(),
+            });
+        }
+        let mistyped = lint_names.mistyped().into_iter().min_by_key(|m| m.0);
+        if let Some((at, name, expected)) = mistyped {
+            let (line, col) = line_col(src, at);
+            return Err(ConfigErr::OptionType {
+                path: path.to_owned(),
+                line,
+                col,
+                name,
+                expected,
+            });
+        }
+        toml::de::from_str(src).map_err(parse_err)
+    }
+    /// The user config, `$XDG_CONFIG_HOME/statix/statix.toml`, or
+    /// `~/.config/statix/statix.toml`
+    pub fn user() -> Result<Option<Self>, ConfigErr> {
+        let absolute = |var| {
+            env::var_os(var)
+                .map(PathBuf::from)
+                .filter(|p| p.is_absolute())
+        };
+        let path = absolute("XDG_CONFIG_HOME")
+            .or_else(|| absolute("HOME").map(|home| home.join(".config")))
+            .map(|config| config.join("statix").join("statix.toml"));
+        match path {
+            Some(path) if path.is_file() => {
+                let conf_file = Self::from_path(path)?;
+                conf_file.check()?;
+                Ok(Some(conf_file))
+            }
+            _ => Ok(None),
+        }
+    }
+    /// The path of the `statix.toml` in `dir` or nearest above it
+    pub fn nearest(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|p| p.join("statix.toml"))
+            .find(|p| p.is_file())
     }
     pub fn discover<P: AsRef<Path>>(path: P) -> Result<Self, ConfigErr> {
         let cannonical_path = fs::canonicalize(path.as_ref()).map_err(ConfigErr::InvalidPath)?;
@@ -299,26 +1056,226 @@ impl ConfFile {
     }
     pub fn dump(&self) -> String {
         let ideal_config = {
+            let enabled = vec![];
             let disabled = vec![];
             let nix_version = Some(utils::default_nix_version());
             let ignore = vec![".direnv".into()];
+            let severity = Default::default();
+            // every option, set to its default
+            let lints = LINTS
+                .iter()
+                .map(|l| {
+                    let options = l
+                        .options()
+                        .iter()
+                        .map(|o| (o.name.to_owned(), toml_value(&o.default)))
+                        .collect::<BTreeMap<_, _>>();
+                    (l.name().to_owned(), options)
+                })
+                .filter(|(_, options)| !options.is_empty())
+                .collect();
+            let root = false;
+            let extends = None;
+            let overrides = vec![];
+            let path = Default::default();
             Self {
+                enabled,
                 disabled,
                 nix_version,
                 ignore,
+                severity,
+                lints,
+                root,
+                extends,
+                overrides,
+                path,
             }
         };
         toml::ser::to_string_pretty(&ideal_config).unwrap()
     }
+    /// Where the config file was read from, empty for the default config
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or_else(|| Path::new(""))
+    }
+    /// Whether settings are not inherited from further up
+    pub fn is_root(&self) -> bool {
+        self.root
+    }
+    pub fn overrides(&self) -> &[Override] {
+        &self.overrides
+    }
+    /// The config for files matched by the overrides at `indices`,
+    /// later overrides win over earlier ones
+    pub fn overridden(&self, indices: &[usize]) -> Self {
+        let mut conf_file = Self {
+            overrides: vec![],
+            ..self.clone()
+        };
+        for o in indices.iter().map(|&i| &self.overrides[i]) {
+            conf_file.layer(
+                &o.enabled,
+                &o.disabled,
+                &o.nix_version,
+                &o.severity,
+                &o.lints,
+            );
+        }
+        conf_file
+    }
+    /// The config file this one inherits from: the one it `extends`,
+    /// or else the nearest one above its directory, unless it is the
+    /// `root`
+    pub fn parent(&self) -> Result<Option<Self>, ConfigErr> {
+        if let Some(extends) = &self.extends {
+            return Self::from_path(self.dir().join(extends)).map(Some);
+        }
+        if self.root || self.path.as_os_str().is_empty() {
+            return Ok(None);
+        }
+        match self.dir().parent().and_then(Self::nearest) {
+            Some(path) => Self::from_path(path).map(Some),
+            None => Ok(None),
+        }
+    }
+    /// This config file along with those it inherits from, outermost
+    /// first
+    pub fn chain(self) -> Result<Vec<Self>, ConfigErr> {
+        let mut chain: Vec<Self> = Vec::new();
+        let mut next = Some(self);
+        while let Some(conf_file) = next {
+            if chain.iter().any(|c| c.path == conf_file.path) {
+                return Err(ConfigErr::ExtendsCycle(conf_file.path));
+            }
+            conf_file.check()?;
+            next = conf_file.parent()?;
+            chain.push(conf_file);
+        }
+        chain.reverse();
+        Ok(chain)
+    }
+    /// Mistakes that parsing does not catch: nix versions that do not
+    /// parse, and globs of overrides that do not compile
+    pub fn check(&self) -> Result<(), ConfigErr> {
+        let versions = self
+            .nix_version
+            .iter()
+            .chain(self.overrides.iter().filter_map(|o| o.nix_version.as_ref()));
+        for v in versions {
+            v.parse::<Version>()
+                .map_err(|_| ConfigErr::ConfFileVersionParse(v.clone()))?;
+        }
+        for o in self.overrides.iter() {
+            o.matcher()?;
+        }
+        Ok(())
+    }
+    /// The settings of this config file on top of those of `base`,
+    /// as with overrides
+    pub fn inherit(&self, base: &Self) -> Self {
+        let mut conf_file = Self {
+            enabled: base.enabled.clone(),
+            disabled: base.disabled.clone(),
+            nix_version: base.nix_version.clone(),
+            severity: base.severity.clone(),
+            lints: base.lints.clone(),
+            ..self.clone()
+        };
+        conf_file.layer(
+            &self.enabled,
+            &self.disabled,
+            &self.nix_version,
+            &self.severity,
+            &self.lints,
+        );
+        conf_file
+    }
+    // `enabled` and `nix_version` replace, `disabled`, `severity` and
+    // options of lints add to the settings so far
+    fn layer(
+        &mut self,
+        enabled: &[String],
+        disabled: &[String],
+        nix_version: &Option<String>,
+        severity: &BTreeMap<String, LintSeverity>,
+        lints: &LintSettings,
+    ) {
+        if !enabled.is_empty() {
+            self.enabled = enabled.to_vec();
+        }
+        self.disabled.extend(disabled.iter().cloned());
+        if nix_version.is_some() {
+            self.nix_version = nix_version.clone();
+        }
+        self.severity
+            .extend(severity.iter().map(|(k, v)| (k.clone(), *v)));
+        for (name, options) in lints.iter() {
+            self.lints
+                .entry(name.clone())
+                .or_default()
+                .extend(options.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+    }
     pub fn lints(&self) -> LintMap {
-        utils::lint_map_of(
+        self.lints_with(&[], &[])
+            .expect("selectors in the config file are not checked")
+    }
+    /// Lints enabled by the config file and by `select`, less those
+    /// disabled by either the config file or `ignore`. `select`
+    /// replaces `enabled` from the config file. Lints are selected by
+    /// category, name, glob of names, or code.
+    pub fn lints_with(&self, select: &[String], ignore: &[String]) -> Result<LintMap, ConfigErr> {
+        // a typo on the command line should not go unnoticed
+        if let Some(unknown) = select
+            .iter()
+            .chain(ignore)
+            .find(|s| !LINTS.iter().any(|l| selects(s, &***l)))
+        {
+            let categories = Category::ALL
+                .iter()
+                .map(Category::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(ConfigErr::UnknownSelector(unknown.clone(), categories));
+        }
+        let enabled = if select.is_empty() {
+            &self.enabled
+        } else {
+            select
+        };
+        let is_enabled = |lint: &dyn Lint| {
+            (enabled.is_empty() || enabled.iter().any(|s| selects(s, lint)))
+                && !self.disabled.iter().chain(ignore).any(|s| selects(s, lint))
+        };
+        let mut lints = utils::lint_map_of(
             (*LINTS)
                 .iter()
-                .filter(|l| !self.disabled.iter().any(|check| check == l.name()))
+                .filter(|l| is_enabled(&****l))
                 .cloned()
                 .collect::<Vec<_>>()
                 .as_slice(),
-        )
+        );
+        for (name, severity) in self.severity.iter() {
+            if let Some(lint) = LINTS.iter().find(|l| l.name() == name) {
+                lints.set_severity(lint.code(), (*severity).into());
+            }
+        }
+        Ok(lints)
+    }
+    /// Options set for lints, as they reach rules through the session
+    pub fn options(&self) -> LintOptions {
+        self.lints
+            .iter()
+            .map(|(name, options)| {
+                let options = options
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.clone(), option_value(v)?)))
+                    .collect();
+                (name.clone(), options)
+            })
+            .collect()
     }
     pub fn version(&self) -> Result<Version, ConfigErr> {
         if let Some(v) = &self.nix_version {
@@ -332,6 +1289,40 @@ impl ConfFile {
     }
 }
 
+// the current directory unless given on the command line
+fn conf_path(conf_path: &Option<PathBuf>) -> &Path {
+    conf_path.as_deref().unwrap_or_else(|| Path::new("."))
+}
+
+// config files are discovered for each file, unless one was named on
+// the command line
+fn profiles(
+    explicit: bool,
+    conf_file: ConfFile,
+    select: &[String],
+    ignore: &[String],
+) -> Result<Profiles, ConfigErr> {
+    if explicit {
+        Profiles::new(conf_file, select, ignore)
+    } else {
+        Profiles::discover(conf_file, select, ignore)
+    }
+}
+
+/// Whether `selector`, a category, a glob of lint names, or a lint
+/// code, selects `lint`
+fn selects(selector: &str, lint: &dyn Lint) -> bool {
+    if let Ok(category) = selector.parse::<Category>() {
+        return lint.category() == category;
+    }
+    if suppression::resolve(selector) == Some(lint.code()) {
+        return true;
+    }
+    Glob::new(selector)
+        .map(|glob| glob.compile_matcher().is_match(lint.name()))
+        .unwrap_or(false)
+}
+
 fn parse_line_col(src: &str) -> Result<(usize, usize), ConfigErr> {
     let parts = src.split(',');
     match parts.collect::<Vec<_>>().as_slice() {
@@ -375,3 +1366,473 @@ fn vfs(files: Vec<PathBuf>) -> Result<ReadOnlyVfs, ConfigErr> {
     }
     Ok(vfs)
 }
+
+pub mod main {
+    use std::{collections::BTreeMap, path::Path};
+
+    use super::{
+        conf_path, selects, ConfFile, Config, ConfigCmd, LintSettings, LintSeverity, Show,
+    };
+    use crate::{
+        err::{ConfigErr, StatixErr},
+        utils,
+    };
+
+    use lib::{session::Version, Lint, LINTS};
+
+    pub fn main(config: Config) -> Result<(), StatixErr> {
+        match config.cmd {
+            ConfigCmd::Check { conf_path } => {
+                // meant for CI, which goes by the exit status
+                if let Err(e) = check(&conf_path) {
+                    eprintln!("{}", StatixErr::from(e));
+                    std::process::exit(1);
+                }
+                Ok(())
+            }
+            ConfigCmd::Show(show_config) => Ok(show(show_config)?),
+        }
+    }
+
+    fn check(conf_path: &Path) -> Result<(), ConfigErr> {
+        let chain = ConfFile::user()?
+            .into_iter()
+            .chain(ConfFile::discover(conf_path)?.chain()?);
+        for conf_file in chain {
+            if conf_file.path().as_os_str().is_empty() {
+                println!("No statix.toml found, the defaults apply");
+            } else {
+                println!("{}: ok", conf_file.path().display());
+            }
+        }
+        Ok(())
+    }
+
+    /// Settings from one place, in the order they are applied
+    pub(super) struct Step {
+        origin: String,
+        /// Patterns of files to skip, relative to the directory of the
+        /// config file
+        pub(super) ignore: Vec<String>,
+        enabled: Vec<String>,
+        disabled: Vec<String>,
+        nix_version: Option<String>,
+        severity: BTreeMap<String, LintSeverity>,
+        lints: LintSettings,
+    }
+
+    pub(super) fn steps(
+        layers: Vec<(ConfFile, Vec<usize>)>,
+        select: &[String],
+        ignore: &[String],
+    ) -> Vec<Step> {
+        let mut steps = Vec::new();
+        for (conf_file, matched) in layers {
+            let origin = conf_file.path().display().to_string();
+            for &i in matched.iter() {
+                let o = &conf_file.overrides[i];
+                steps.push(Step {
+                    origin: format!("{}, overrides #{}", origin, i + 1),
+                    ignore: vec![],
+                    enabled: o.enabled.clone(),
+                    disabled: o.disabled.clone(),
+                    nix_version: o.nix_version.clone(),
+                    severity: o.severity.clone(),
+                    lints: o.lints.clone(),
+                });
+            }
+            // overrides come after the rest of their config file
+            let at = steps.len() - matched.len();
+            steps.insert(
+                at,
+                Step {
+                    origin,
+                    ignore: conf_file.ignore,
+                    enabled: conf_file.enabled,
+                    disabled: conf_file.disabled,
+                    nix_version: conf_file.nix_version,
+                    severity: conf_file.severity,
+                    lints: conf_file.lints,
+                },
+            );
+        }
+        let flag = |origin: &str| Step {
+            origin: origin.to_owned(),
+            ignore: vec![],
+            enabled: vec![],
+            disabled: vec![],
+            nix_version: None,
+            severity: BTreeMap::new(),
+            lints: BTreeMap::new(),
+        };
+        steps.push(Step {
+            enabled: select.to_vec(),
+            ..flag("--select")
+        });
+        steps.push(Step {
+            disabled: ignore.to_vec(),
+            ..flag("--ignore-lint")
+        });
+        steps
+    }
+
+    /// The severity of `lint`, `None` if it is disabled, along with why
+    pub(super) fn lint_state(steps: &[Step], lint: &dyn Lint) -> (Option<LintSeverity>, String) {
+        let not_enabled = steps
+            .iter()
+            .rev()
+            .find(|s| !s.enabled.is_empty())
+            .filter(|s| !s.enabled.iter().any(|sel| selects(sel, lint)));
+        let disabled = steps.iter().find_map(|s| {
+            s.disabled
+                .iter()
+                .find(|sel| selects(sel, lint))
+                .map(|sel| (s, sel))
+        });
+        let severity = steps
+            .iter()
+            .rev()
+            .find_map(|s| s.severity.get(lint.name()).map(|severity| (s, severity)));
+        if let Some(step) = not_enabled {
+            (None, format!("not enabled by {}", step.origin))
+        } else if let Some((step, sel)) = disabled {
+            (None, format!("disabled by `{}` in {}", sel, step.origin))
+        } else if let Some((step, severity)) = severity {
+            (Some(*severity), format!("set in {}", step.origin))
+        } else {
+            (Some(LintSeverity::Warn), "default".to_owned())
+        }
+    }
+
+    fn show(show: Show) -> Result<(), ConfigErr> {
+        let conf_file = ConfFile::discover(conf_path(&show.conf_path))?;
+        let profiles = super::profiles(
+            show.conf_path.is_some(),
+            conf_file,
+            &show.select,
+            &show.ignore_lint,
+        )?;
+        let steps = steps(
+            profiles.layers(&show.target)?,
+            &show.select,
+            &show.ignore_lint,
+        );
+
+        println!("# config of {}", show.target.display());
+        let from_nix = utils::get_version_info().filter(|v| v.parse::<Version>().is_ok());
+        match steps.iter().rev().find(|s| s.nix_version.is_some()) {
+            Some(step) => println!(
+                "nix_version = {:?} # {}",
+                step.nix_version.as_ref().unwrap(),
+                step.origin
+            ),
+            None => match from_nix {
+                Some(v) => println!("nix_version = {:?} # nix --version", v),
+                None => println!("nix_version = {:?} # default", utils::default_nix_version()),
+            },
+        }
+        for step in steps.iter().filter(|s| !s.ignore.is_empty()) {
+            println!("ignore = {:?} # {}", step.ignore, step.origin);
+        }
+
+        println!();
+        for lint in LINTS.iter() {
+            let (severity, why) = lint_state(&steps, &***lint);
+            let severity = severity.map(LintSeverity::as_str).unwrap_or("off");
+            println!(
+                "{} {:<24} {:<5} # {}",
+                utils::code(lint.code()),
+                lint.name(),
+                severity,
+                why
+            );
+        }
+
+        for lint in LINTS.iter() {
+            let options = lint.options();
+            if options.is_empty() {
+                continue;
+            }
+            println!("\n[lints.{}]", lint.name());
+            for option in options {
+                let set = steps.iter().rev().find_map(|s| {
+                    let value = s.lints.get(lint.name())?.get(option.name)?;
+                    Some((value, s))
+                });
+                match set {
+                    Some((value, step)) => {
+                        println!("{} = {} # {}", option.name, value, step.origin)
+                    }
+                    None => println!("{} = {} # default", option.name, option.default),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled(conf_file: &ConfFile, select: &[&str], ignore: &[&str]) -> Vec<&'static str> {
+        let owned = |s: &[&str]| s.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let lints = conf_file
+            .lints_with(&owned(select), &owned(ignore))
+            .unwrap();
+        let mut names = lints
+            .values()
+            .flatten()
+            .map(|l| l.name())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    #[test]
+    fn select_by_category() {
+        let conf_file = ConfFile::default();
+        assert_eq!(
+            enabled(&conf_file, &["perf"], &[]),
+            vec!["faster_groupby", "faster_zipattrswith"]
+        );
+    }
+
+    #[test]
+    fn select_by_glob_and_code() {
+        let conf_file = ConfFile::default();
+        assert_eq!(
+            enabled(&conf_file, &["faster_*", "W08"], &["W15"]),
+            vec!["faster_zipattrswith", "useless_parens"]
+        );
+    }
+
+    #[test]
+    fn select_replaces_enabled() {
+        let conf_file: ConfFile = toml::de::from_str(
+            r#"
+            enabled = ["deprecated"]
+            disabled = ["faster_groupby"]
+            "#,
+        )
+        .unwrap();
+        assert!(enabled(&conf_file, &[], &[]).contains(&"legacy_let_syntax"));
+        assert_eq!(
+            enabled(&conf_file, &["perf"], &[]),
+            vec!["faster_zipattrswith"]
+        );
+    }
+
+    #[test]
+    fn unknown_selector() {
+        let conf_file = ConfFile::default();
+        assert!(matches!(
+            conf_file.lints_with(&["bogus".into()], &[]),
+            Err(ConfigErr::UnknownSelector(..))
+        ));
+    }
+
+    #[test]
+    fn inherit() {
+        let base: ConfFile = toml::de::from_str(
+            r#"
+            enabled = ["style"]
+            disabled = ["eta_reduction"]
+            nix_version = "2.3"
+            "#,
+        )
+        .unwrap();
+        let child: ConfFile = toml::de::from_str(
+            r#"
+            disabled = ["useless_parens"]
+            [severity]
+            manual_inherit = "error"
+            "#,
+        )
+        .unwrap();
+        let conf_file = child.inherit(&base);
+        let names = enabled(&conf_file, &[], &[]);
+        assert!(names.contains(&"manual_inherit"));
+        assert!(!names.contains(&"eta_reduction"));
+        assert!(!names.contains(&"useless_parens"));
+        assert!(!names.contains(&"faster_groupby"));
+        assert_eq!(conf_file.nix_version.as_deref(), Some("2.3"));
+        assert!(conf_file.severity.contains_key("manual_inherit"));
+    }
+
+    fn mistake(src: &str) -> String {
+        ConfFile::parse(src, Path::new("statix.toml"))
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn show_agrees() {
+        let conf_file: ConfFile = toml::de::from_str(
+            r#"
+            enabled = ["style", "perf"]
+            disabled = ["W03"]
+            [[overrides]]
+            files = ["*.nix"]
+            disabled = ["faster_*"]
+            "#,
+        )
+        .unwrap();
+        let select = ["style".to_owned(), "correctness".to_owned()];
+        let ignore = ["eta_reduction".to_owned()];
+        let steps = main::steps(vec![(conf_file.clone(), vec![0])], &select, &ignore);
+        let mut shown = LINTS
+            .iter()
+            .filter(|l| main::lint_state(&steps, &****l).0.is_some())
+            .map(|l| l.name())
+            .collect::<Vec<_>>();
+        shown.sort_unstable();
+        let expected = enabled(
+            &conf_file.overridden(&[0]),
+            &["style", "correctness"],
+            &["eta_reduction"],
+        );
+        assert_eq!(shown, expected);
+    }
+
+    #[test]
+    fn show_every_ignore() {
+        let user: ConfFile = toml::de::from_str("ignore = [\"result\"]").unwrap();
+        let project: ConfFile = toml::de::from_str(
+            r#"
+            ignore = [".direnv"]
+            [[overrides]]
+            files = ["*.nix"]
+            "#,
+        )
+        .unwrap();
+        let steps = main::steps(vec![(user, vec![]), (project, vec![0])], &[], &[]);
+        let shown = steps
+            .iter()
+            .filter(|s| !s.ignore.is_empty())
+            .map(|s| s.ignore.as_slice())
+            .collect::<Vec<_>>();
+        assert_eq!(shown, vec![["result"], [".direnv"]]);
+    }
+
+    #[test]
+    fn every_key() {
+        let src = r#"
+            enabled = ["style"]
+            disabled = ["W03"]
+            nix_version = "2.4"
+            ignore = [".direnv"]
+            root = true
+            extends = "../statix.toml"
+            [severity]
+            eta_reduction = "hint"
+            [lints.repeated_keys]
+            min_occurrences = 2
+            [[overrides]]
+            files = ["*.nix"]
+            enabled = ["perf"]
+            disabled = ["faster_*"]
+            nix_version = "2.3"
+            severity = { faster_groupby = "error" }
+            lints = { useless_parens = { primitives = ["list"] } }
+            "#;
+        let conf_file = ConfFile::parse(src, Path::new("statix.toml")).unwrap();
+        assert!(conf_file.check().is_ok());
+    }
+
+    #[test]
+    fn unknown_names() {
+        assert_eq!(
+            mistake("disabled = [\n  \"manual_inhert\",\n]\n"),
+            "statix.toml:2:3: unknown lint `manual_inhert`, did you mean `manual_inherit`?"
+        );
+        assert_eq!(
+            mistake("root = false\ndisabeld = []\n"),
+            "statix.toml:2:1: unknown key `disabeld`, did you mean `disabled`?"
+        );
+        assert_eq!(
+            mistake("[[overrides]]\nfiles = []\nseverity = { bogus = \"hint\" }\n"),
+            "statix.toml:3:14: unknown lint `bogus`"
+        );
+    }
+
+    #[test]
+    fn lint_options() {
+        assert_eq!(
+            mistake("[lints.repeated_keys]\nmin_ocurrences = 2\n"),
+            "statix.toml:2:1: unknown option `min_ocurrences`, did you mean `min_occurrences`?"
+        );
+        assert_eq!(
+            mistake("[lints.empty_pattern]\nmin_occurrences = 2\n"),
+            "statix.toml:2:1: unknown option `min_occurrences`"
+        );
+        assert_eq!(
+            mistake("[lints.useless_parens]\nprimitives = [\"list\", 1]\n"),
+            "statix.toml:2:14: `useless_parens.primitives` takes a list of strings"
+        );
+        assert_eq!(
+            mistake("[lints.useless_parens]\nprimitives = [\"list\", \"atrset\"]\n"),
+            "statix.toml:2:14: unknown value `atrset`, did you mean `attrset`?"
+        );
+
+        let conf_file = ConfFile::parse(
+            r#"
+            [lints.repeated_keys]
+            min_occurrences = 2
+            [[overrides]]
+            files = ["*.nix"]
+            lints = { repeated_keys = { min_occurrences = 4 } }
+            "#,
+            Path::new("statix.toml"),
+        )
+        .unwrap();
+        let min_occurrences =
+            |conf_file: &ConfFile| conf_file.options()["repeated_keys"]["min_occurrences"].clone();
+        assert_eq!(min_occurrences(&conf_file), OptionValue::Int(2));
+        assert_eq!(
+            min_occurrences(&conf_file.overridden(&[0])),
+            OptionValue::Int(4)
+        );
+    }
+
+    #[test]
+    fn explicit_config_turns_off_discovery() {
+        let root = crate::utils::tree(
+            "explicit",
+            &[
+                ("statix.toml", "disabled = [\"empty_pattern\"]"),
+                ("sub/statix.toml", "root = true"),
+                ("sub/a.nix", ""),
+            ],
+        );
+        let empty_pattern = |args: &[&str]| {
+            let check = Check::parse_from(args);
+            let conf_file = ConfFile::discover(check.conf_path()).unwrap();
+            let profile = check
+                .profiles(conf_file)
+                .unwrap()
+                .get(&root.join("sub/a.nix"))
+                .unwrap();
+            let enabled = profile
+                .lints
+                .values()
+                .flatten()
+                .any(|l| l.name() == "empty_pattern");
+            enabled
+        };
+        let root_str = root.to_str().unwrap();
+        let config = root.join("statix.toml");
+
+        assert!(empty_pattern(&["check", root_str]));
+        assert!(!empty_pattern(&["check", "-c", root_str, root_str]));
+        assert!(!empty_pattern(&[
+            "check",
+            "-c",
+            config.to_str().unwrap(),
+            root_str
+        ]));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
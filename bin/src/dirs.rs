@@ -4,22 +4,41 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::dirs;
+use crate::{dirs, profile::Profiles};
 
 use ignore::{
     gitignore::{Gitignore, GitignoreBuilder},
-    Error as IgnoreError, Match,
+    Error as IgnoreError,
 };
 
-#[derive(Debug)]
-pub struct Walker {
+/// What to skip: paths matched by `.gitignore` and `--ignore`, or by
+/// the `ignore` list of a config file that applies to them
+pub struct Ignore<'a> {
+    gitignore: Gitignore,
+    profiles: &'a Profiles,
+}
+
+impl<'a> Ignore<'a> {
+    pub fn new(gitignore: Gitignore, profiles: &'a Profiles) -> Self {
+        Self {
+            gitignore,
+            profiles,
+        }
+    }
+
+    pub fn matched(&self, path: &Path, is_dir: bool) -> bool {
+        self.gitignore.matched(path, is_dir).is_ignore() || self.profiles.ignores(path, is_dir)
+    }
+}
+
+pub struct Walker<'a> {
     dirs: Vec<PathBuf>,
     files: Vec<PathBuf>,
-    ignore: Gitignore,
+    ignore: &'a Ignore<'a>,
 }
 
-impl Walker {
-    pub fn new<P: AsRef<Path>>(target: P, ignore: Gitignore) -> io::Result<Self> {
+impl<'a> Walker<'a> {
+    pub fn new<P: AsRef<Path>>(target: P, ignore: &'a Ignore<'a>) -> io::Result<Self> {
         let target = target.as_ref().to_path_buf();
         if !target.exists() {
             Err(Error::new(
@@ -42,32 +61,26 @@ impl Walker {
     }
 }
 
-impl Iterator for Walker {
+impl Iterator for Walker<'_> {
     type Item = PathBuf;
     fn next(&mut self) -> Option<Self::Item> {
         self.files.pop().or_else(|| {
             while let Some(dir) = self.dirs.pop() {
-                if dir.is_dir() {
-                    if let Match::None | Match::Whitelist(_) = self.ignore.matched(&dir, true) {
-                        let mut found = false;
-                        for entry in fs::read_dir(&dir).ok()? {
-                            let entry = entry.ok()?;
-                            let path = entry.path();
-                            if path.is_dir() {
-                                self.dirs.push(path);
-                            } else if path.is_file() {
-                                if let Match::None | Match::Whitelist(_) =
-                                    self.ignore.matched(&path, false)
-                                {
-                                    found = true;
-                                    self.files.push(path);
-                                }
-                            }
-                        }
-                        if found {
-                            break;
+                if dir.is_dir() && !self.ignore.matched(&dir, true) {
+                    let mut found = false;
+                    for entry in fs::read_dir(&dir).ok()? {
+                        let entry = entry.ok()?;
+                        let path = entry.path();
+                        if path.is_dir() {
+                            self.dirs.push(path);
+                        } else if path.is_file() && !self.ignore.matched(&path, false) {
+                            found = true;
+                            self.files.push(path);
                         }
                     }
+                    if found {
+                        break;
+                    }
                 }
             }
             self.files.pop()
@@ -104,10 +117,10 @@ pub fn build_ignore_set<P: AsRef<Path>>(
     gitignore.build()
 }
 
-pub fn walk_nix_files<P: AsRef<Path>>(
-    ignore: Gitignore,
+pub fn walk_nix_files<'a, P: AsRef<Path>>(
+    ignore: &'a Ignore<'a>,
     target: P,
-) -> Result<impl Iterator<Item = PathBuf>, io::Error> {
+) -> Result<impl Iterator<Item = PathBuf> + 'a, io::Error> {
     let walker = dirs::Walker::new(target, ignore)?;
     Ok(walker.filter(|path: &PathBuf| matches!(path.extension(), Some(e) if e == "nix")))
 }
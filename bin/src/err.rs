@@ -12,10 +12,33 @@ pub enum ConfigErr {
     InvalidPosition(String),
     #[error("unable to parse `{0}` as warning code")]
     InvalidWarningCode(String),
-    #[error("unable to parse config file: {0}")]
-    ConfFileParse(toml::de::Error),
+    #[error("unable to parse config file `{}`: {1}", .0.display())]
+    ConfFileParse(std::path::PathBuf, toml::de::Error),
     #[error("unable to parse nix version: `{0}`")]
     ConfFileVersionParse(String),
+    #[error("`{0}` is neither a lint category nor matches any lint, categories are: {1}")]
+    UnknownSelector(String, String),
+    #[error("invalid glob in overrides: {0}")]
+    OverrideGlob(globset::Error),
+    #[error("`{}` extends itself", .0.display())]
+    ExtendsCycle(std::path::PathBuf),
+    #[error("{}:{line}:{col}: unknown {what} `{name}`{hint}", path.display())]
+    Unknown {
+        path: std::path::PathBuf,
+        line: usize,
+        col: usize,
+        what: &'static str,
+        name: String,
+        hint: String,
+    },
+    #[error("{}:{line}:{col}: `{name}` takes a {expected}", path.display())]
+    OptionType {
+        path: std::path::PathBuf,
+        line: usize,
+        col: usize,
+        name: String,
+        expected: &'static str,
+    },
 }
 
 // #[derive(Error, Debug)]
@@ -30,6 +53,10 @@ pub enum FixErr {
     // Parse(PathBuf, ParseError),
     #[error("path error: {0}")]
     InvalidPath(#[from] io::Error),
+    #[error("fixes did not settle after {0} passes, lints that kept firing: {1}")]
+    NoConvergence(usize, String),
+    #[error("fixes undo each other, lints involved: {0}")]
+    Cycle(String),
 }
 
 #[derive(Error, Debug)]
@@ -42,6 +69,8 @@ pub enum SingleFixErr {
     Conversion(usize),
     #[error("nothing to fix")]
     NoOp,
+    #[error("the fix by {0} was rolled back, {1}")]
+    RolledBack(String, crate::fix::Rejection),
 }
 
 #[derive(Error, Debug)]
@@ -50,6 +79,43 @@ pub enum ExplainErr {
     LintNotFound(u32),
 }
 
+#[derive(Error, Debug)]
+pub enum LspErr {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid header `{0}`")]
+    InvalidHeader(String),
+    #[error("missing Content-Length header")]
+    MissingContentLength,
+    #[error("exit notification received before shutdown request")]
+    ExitBeforeShutdown,
+}
+
+#[derive(Error, Debug)]
+pub enum WatchErr {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum CacheErr {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("no cache directory, neither XDG_CACHE_HOME nor HOME is set")]
+    NoCacheDir,
+}
+
+#[cfg(feature = "json")]
+#[derive(Error, Debug)]
+pub enum BaselineErr {
+    #[error("unable to access `{0}`: {1}")]
+    InvalidPath(std::path::PathBuf, io::Error),
+    #[error("unable to parse `{0}`: {1}")]
+    Parse(std::path::PathBuf, serde_json::Error),
+    #[error("unsupported baseline version `{0}`")]
+    UnsupportedVersion(u32),
+}
+
 #[derive(Error, Debug)]
 pub enum StatixErr {
     // #[error("linter error: {0}")]
@@ -62,4 +128,13 @@ pub enum StatixErr {
     Config(#[from] ConfigErr),
     #[error("explain error: {0}")]
     Explain(#[from] ExplainErr),
+    #[error("language server error: {0}")]
+    Lsp(#[from] LspErr),
+    #[error("watch error: {0}")]
+    Watch(#[from] WatchErr),
+    #[error("cache error: {0}")]
+    Cache(#[from] CacheErr),
+    #[cfg(feature = "json")]
+    #[error("baseline error: {0}")]
+    Baseline(#[from] BaselineErr),
 }
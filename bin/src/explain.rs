@@ -1,18 +1,40 @@
 use crate::{err::ExplainErr, utils};
 
-pub fn explain(code: u32) -> Result<&'static str, ExplainErr> {
+use lib::Lint;
+
+pub fn explain(code: u32) -> Result<String, ExplainErr> {
     let lints = utils::lint_map();
     match code {
-        0 => Ok("syntax error"),
+        0 => Ok("syntax error".to_owned()),
         _ => lints
             .values()
             .flatten()
             .find(|l| l.code() == code)
-            .map(|l| l.explanation())
+            .map(|l| with_options(&***l))
             .ok_or(ExplainErr::LintNotFound(code)),
     }
 }
 
+// the explanation of a lint, followed by the options it takes
+fn with_options(lint: &dyn Lint) -> String {
+    let mut explanation = lint.explanation().trim_end().to_owned();
+    let options = lint.options();
+    if options.is_empty() {
+        return explanation;
+    }
+    explanation.push_str(&format!(
+        "\n\n## Options\nSet in `statix.toml`, under `[lints.{}]`:\n",
+        lint.name()
+    ));
+    for option in options {
+        explanation.push_str(&format!(
+            "\n- `{}` ({}, default `{}`): {}",
+            option.name, option.ty, option.default, option.doc
+        ));
+    }
+    explanation
+}
+
 pub mod main {
 
     use crate::{config::Explain as ExplainConfig, err::StatixErr};
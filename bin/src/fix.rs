@@ -2,7 +2,7 @@ use std::borrow::Cow;
 
 use crate::LintMap;
 
-use lib::session::SessionInfo;
+use lib::{session::SessionInfo, Applicability, Report};
 use rnix::TextRange;
 
 mod all;
@@ -11,13 +11,27 @@ use all::all_with;
 mod single;
 use single::single;
 
+mod verify;
+pub use verify::Rejection;
+
 type Source<'a> = Cow<'a, str>;
 
 pub struct FixResult<'a> {
     pub src: Source<'a>,
     pub fixed: Vec<Fixed>,
+    /// Fixes that overlapped other fixes, and no longer applied once
+    /// those were made
+    pub skipped: Vec<Skipped>,
+    /// Fixes that did not hold up once applied, they were undone
+    pub rolled_back: Vec<RolledBack>,
+    deferred: Vec<all::Deferred>,
+    /// Rolled back fixes, by code and range in the current source,
+    /// they are not tried again
+    rejected: Vec<(u32, TextRange)>,
     pub lints: &'a LintMap,
     pub sess: &'a SessionInfo,
+    /// The least safe fixes to apply
+    pub applicability: Applicability,
 }
 
 #[derive(Debug, Clone)]
@@ -26,73 +40,222 @@ pub struct Fixed {
     pub code: u32,
 }
 
+#[derive(Debug, Clone)]
+pub struct Skipped {
+    pub code: u32,
+    /// The text the fix would have replaced
+    pub text: String,
+    /// Code of the lint whose fix was made instead
+    pub blocked_by: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RolledBack {
+    pub code: u32,
+    /// The text the fix would have replaced
+    pub text: String,
+    pub reason: Rejection,
+}
+
 impl<'a> FixResult<'a> {
-    fn empty(src: Source<'a>, lints: &'a LintMap, sess: &'a SessionInfo) -> Self {
+    fn empty(
+        src: Source<'a>,
+        lints: &'a LintMap,
+        sess: &'a SessionInfo,
+        applicability: Applicability,
+    ) -> Self {
         Self {
             src,
             fixed: Vec::new(),
+            skipped: Vec::new(),
+            rolled_back: Vec::new(),
+            deferred: Vec::new(),
+            rejected: Vec::new(),
             lints,
             sess,
+            applicability,
         }
     }
 }
 
+/// Drop suggestions that are less safe than `applicability`
+fn applicable(mut report: Report, applicability: Applicability) -> Report {
+    for diagnostic in report.diagnostics.iter_mut() {
+        if diagnostic
+            .suggestion
+            .as_ref()
+            .is_some_and(|s| s.applicability > applicability)
+        {
+            diagnostic.suggestion = None;
+        }
+    }
+    report
+}
+
 pub mod main {
-    use std::borrow::Cow;
+    use std::{borrow::Cow, fs};
 
     use crate::{
         config::{
             FixOut, Single as SingleConfig, {ConfFile, Fix as FixConfig},
         },
-        err::{FixErr, StatixErr},
+        err::{FixErr, StatixErr, WatchErr},
+        profile::Profiles,
+        utils,
+        watch::{self, Watcher},
     };
 
-    use lib::session::SessionInfo;
+    use super::single::{byte_to_pos, Candidate};
+    use rnix::TextRange;
     use similar::TextDiff;
+    use vfs::{ReadOnlyVfs, VfsEntry};
 
     pub fn all(fix_config: FixConfig) -> Result<(), StatixErr> {
-        let conf_file = ConfFile::discover(&fix_config.conf_path)?;
-        let vfs = fix_config.vfs(conf_file.ignore.as_slice())?;
-
-        let lints = conf_file.lints();
-        let version = conf_file.version()?;
+        if fix_config.watch {
+            return watch(fix_config);
+        }
 
-        let session = SessionInfo::from_version(version);
+        let conf_file = ConfFile::discover(fix_config.conf_path())?;
+        let profiles = fix_config.profiles(conf_file)?;
+        let vfs = fix_config.vfs(&fix_config.ignore(&profiles)?)?;
 
+        let mut settled = true;
         for entry in vfs.iter() {
-            match (
-                fix_config.out(),
-                super::all_with(entry.contents, &lints, &session),
-            ) {
-                (FixOut::Diff, fix_result) => {
-                    let src = fix_result
-                        .map(|r| r.src)
-                        .unwrap_or(Cow::Borrowed(entry.contents));
-                    let text_diff = TextDiff::from_lines(entry.contents, &src);
-                    let old_file = format!("{}", entry.file_path.display());
-                    let new_file = format!("{} [fixed]", entry.file_path.display());
-                    println!(
-                        "{}",
-                        text_diff
-                            .unified_diff()
-                            .context_radius(4)
-                            .header(&old_file, &new_file)
-                    );
-                }
-                (FixOut::Stream, fix_result) => {
-                    let src = fix_result
-                        .map(|r| r.src)
-                        .unwrap_or(Cow::Borrowed(entry.contents));
-                    println!("{}", &src)
+            settled &= fix_entry(entry, &fix_config, &profiles)?;
+        }
+        if !settled {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+
+    /// Fix a single file, files whose fixes do not settle are reported
+    /// and left alone, `Ok(false)` is returned for those
+    fn fix_entry(
+        entry: VfsEntry,
+        fix_config: &FixConfig,
+        profiles: &Profiles,
+    ) -> Result<bool, StatixErr> {
+        let profile = profiles.get(entry.file_path)?;
+        let (fix_result, settled) = match super::all_with(
+            entry.contents,
+            &profile.lints,
+            &profile.session,
+            fix_config.max_passes,
+            fix_config.applicability(),
+        ) {
+            Ok(fix_result) => (fix_result, true),
+            Err(e) => {
+                eprintln!("{}: {}", entry.file_path.display(), e);
+                (None, false)
+            }
+        };
+        for rolled_back in fix_result.iter().flat_map(|r| r.rolled_back.iter()) {
+            eprintln!(
+                "{}: rolled back a fix by {} {} on `{}`, {}",
+                entry.file_path.display(),
+                utils::code(rolled_back.code),
+                utils::lint_name(rolled_back.code),
+                first_line(&rolled_back.text),
+                rolled_back.reason,
+            );
+        }
+        for skipped in fix_result.iter().flat_map(|r| r.skipped.iter()) {
+            eprintln!(
+                "{}: skipped a fix by {} {} on `{}`, it overlapped a fix by {} {}",
+                entry.file_path.display(),
+                utils::code(skipped.code),
+                utils::lint_name(skipped.code),
+                first_line(&skipped.text),
+                utils::code(skipped.blocked_by),
+                utils::lint_name(skipped.blocked_by),
+            );
+        }
+        match (fix_config.out(), fix_result) {
+            (FixOut::Diff, fix_result) => {
+                let src = fix_result
+                    .map(|r| r.src)
+                    .unwrap_or(Cow::Borrowed(entry.contents));
+                let text_diff = TextDiff::from_lines(entry.contents, &src);
+                let old_file = format!("{}", entry.file_path.display());
+                let new_file = format!("{} [fixed]", entry.file_path.display());
+                println!(
+                    "{}",
+                    text_diff
+                        .unified_diff()
+                        .context_radius(4)
+                        .header(&old_file, &new_file)
+                );
+            }
+            (FixOut::Stream, fix_result) => {
+                let src = fix_result
+                    .map(|r| r.src)
+                    .unwrap_or(Cow::Borrowed(entry.contents));
+                println!("{}", &src)
+            }
+            // rolled back fixes alone leave nothing to write
+            (FixOut::Write, Some(fix_result)) if !fix_result.fixed.is_empty() => {
+                let path = entry.file_path;
+                std::fs::write(path, &*fix_result.src).map_err(FixErr::InvalidPath)?;
+            }
+            _ => (),
+        };
+        Ok(settled)
+    }
+
+    // `first line ...`
+    fn first_line(text: &str) -> String {
+        let mut lines = text.lines();
+        let first = lines.next().unwrap_or_default();
+        if lines.next().is_some() {
+            format!("{} ...", first)
+        } else {
+            first.to_owned()
+        }
+    }
+
+    /// Fix every file, then fix files again as they change. Writing
+    /// a fix changes the file too, but fixing it again is a no-op.
+    fn watch(fix_config: FixConfig) -> Result<(), StatixErr> {
+        let target = fix_config.target();
+        loop {
+            let conf_file = ConfFile::discover(fix_config.conf_path())?;
+            let profiles = fix_config.profiles(conf_file)?;
+            let ignore = fix_config.ignore(&profiles)?;
+            let vfs = fix_config.vfs(&ignore)?;
+
+            let mut watcher = Watcher::new().map_err(WatchErr::from)?;
+            watcher
+                .watch_target(target, fix_config.conf_path(), &ignore)
+                .map_err(WatchErr::from)?;
+
+            for entry in vfs.iter() {
+                fix_entry(entry, &fix_config, &profiles)?;
+            }
+
+            loop {
+                // config files are read as the files they apply to come up
+                let conf_files = profiles.conf_files();
+                watcher
+                    .watch_conf_files(&conf_files)
+                    .map_err(WatchErr::from)?;
+                let changes = watcher.wait().map_err(WatchErr::from)?;
+                if watch::needs_reload(&changes, &conf_files) {
+                    break;
                 }
-                (FixOut::Write, Some(fix_result)) => {
-                    let path = entry.file_path;
-                    std::fs::write(path, &*fix_result.src).map_err(FixErr::InvalidPath)?;
+                let (modified, _) = watch::nix_files(&mut watcher, changes, target, &ignore)
+                    .map_err(WatchErr::from)?;
+                for path in modified {
+                    // the file may be gone again by the time it is read
+                    if let Ok(src) = fs::read_to_string(&path) {
+                        let vfs = ReadOnlyVfs::singleton(&path, src.as_bytes());
+                        for entry in vfs.iter() {
+                            fix_entry(entry, &fix_config, &profiles)?;
+                        }
+                    }
                 }
-                _ => (),
-            };
+            }
         }
-        Ok(())
     }
 
     pub fn single(single_config: SingleConfig) -> Result<(), StatixErr> {
@@ -100,17 +263,38 @@ pub mod main {
         let entry = vfs.iter().next().unwrap();
         let path = entry.file_path.display().to_string();
         let original_src = entry.contents;
-        let (line, col) = single_config.position;
 
-        let conf_file = ConfFile::discover(&single_config.conf_path)?;
+        let conf_file = ConfFile::discover(single_config.conf_path())?;
+        // the entry is named after stdin either way
+        let file_path = single_config.target.as_deref().unwrap_or(entry.file_path);
+        let profile = single_config.profiles(conf_file)?.get(file_path)?;
+        let (lints, session) = (&profile.lints, &profile.session);
 
-        let version = conf_file.version()?;
-
-        let session = SessionInfo::from_version(version);
+        if single_config.list {
+            let candidates = super::single::candidates(
+                &single_config.position,
+                original_src,
+                lints,
+                session,
+                single_config.applicability(),
+            )?;
+            #[cfg(feature = "json")]
+            if single_config.json {
+                return list_json(&candidates, original_src);
+            }
+            return list(&candidates, original_src);
+        }
 
         match (
             single_config.out(),
-            super::single(line, col, original_src, &session),
+            super::single(
+                &single_config.position,
+                single_config.code,
+                original_src,
+                lints,
+                session,
+                single_config.applicability(),
+            ),
         ) {
             (FixOut::Diff, single_result) => {
                 let fixed_src = single_result
@@ -141,4 +325,52 @@ pub mod main {
         };
         Ok(())
     }
+
+    /// Print fixes available at a position, ranges are printed in the
+    /// format accepted by `--position`
+    fn list(candidates: &[Candidate], src: &str) -> Result<(), StatixErr> {
+        for c in candidates {
+            let at = c.diagnostic.at;
+            println!(
+                "{} {} {} {}",
+                utils::code(c.code),
+                utils::lint_name(c.code),
+                selection(at, src),
+                c.diagnostic.message
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    fn list_json(candidates: &[Candidate], src: &str) -> Result<(), StatixErr> {
+        #[derive(serde::Serialize)]
+        struct JsonCandidate<'μ> {
+            code: u32,
+            name: &'static str,
+            message: &'μ str,
+            at: String,
+            applicability: Option<lib::Applicability>,
+        }
+
+        let candidates = candidates
+            .iter()
+            .map(|c| JsonCandidate {
+                code: c.code,
+                name: utils::lint_name(c.code),
+                message: &c.diagnostic.message,
+                at: selection(c.diagnostic.at, src),
+                applicability: c.diagnostic.suggestion.as_ref().map(|s| s.applicability),
+            })
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&candidates).unwrap());
+        Ok(())
+    }
+
+    // `line,col-line,col`
+    fn selection(at: TextRange, src: &str) -> String {
+        let (start_line, start_col) = byte_to_pos(at.start(), src);
+        let (end_line, end_col) = byte_to_pos(at.end(), src);
+        format!("{},{}-{},{}", start_line, start_col, end_line, end_col)
+    }
 }
@@ -1,99 +1,600 @@
 use std::borrow::Cow;
 
-use lib::{session::SessionInfo, Report};
-use rnix::{parser::ParseError as RnixParseErr, WalkEvent};
+use lib::{session::SessionInfo, suppression::Suppressions, Applicability, Report};
+use rnix::{parser::ParseError as RnixParseErr, TextRange, TextSize, WalkEvent};
 
 use crate::{
-    fix::{FixResult, Fixed},
-    LintMap,
+    err::FixErr,
+    fix::{applicable, verify, FixResult, Fixed, RolledBack, Skipped},
+    utils, LintMap,
 };
 
 fn collect_fixes(
     source: &str,
     lints: &LintMap,
     sess: &SessionInfo,
+    applicability: Applicability,
 ) -> Result<Vec<Report>, RnixParseErr> {
     let parsed = rnix::parse(source).as_result()?;
+    let sess = sess.with_scopes(&parsed.node());
 
-    Ok(parsed
+    let reports = parsed
         .node()
         .preorder_with_tokens()
         .filter_map(|event| match event {
             WalkEvent::Enter(child) => lints.get(&child.kind()).map(|rules| {
                 rules
                     .iter()
-                    .filter_map(|rule| rule.validate(&child, sess))
-                    .filter(|report| report.total_suggestion_range().is_some())
+                    .filter_map(|rule| rule.check(&child, &sess))
                     .collect::<Vec<_>>()
             }),
             _ => None,
         })
-        .flatten()
+        .flatten();
+
+    Ok(Suppressions::new(&parsed.node())
+        .apply(reports)
+        .into_iter()
+        .map(|report| applicable(report, applicability))
+        .filter(|report| report.total_suggestion_range().is_some())
         .collect())
 }
 
-fn reorder(mut reports: Vec<Report>) -> Vec<Report> {
-    use std::collections::VecDeque;
+/// Pick the fixes to apply in a single pass: a maximal set of fixes
+/// that do not overlap, fixes overlap if any of their edits do. Fixes
+/// are considered in order of priority, and each one is picked unless
+/// it overlaps a fix picked before it:
+///
+/// - fixes to smaller ranges come first, so that nested fixes are
+///   applied before the fixes around them, which see the result in the
+///   next pass
+/// - then fixes that start earlier in the source
+/// - then fixes of lints with lower codes
+///
+/// Fixes that are not picked are returned along with the code of the
+/// picked fix that they overlap, they are retried in the next pass.
+fn resolve(mut reports: Vec<Report>) -> (Vec<Report>, Vec<(Report, u32)>) {
+    reports.sort_by_key(|r| (r.range().len(), r.range().start(), r.code));
 
-    reports.sort_by(|a, b| {
-        let a_range = a.range();
-        let b_range = b.range();
-        a_range.end().partial_cmp(&b_range.end()).unwrap()
-    });
+    let mut picked: Vec<Report> = Vec::new();
+    let mut deferred = Vec::new();
+    for report in reports {
+        let conflicts = |p: &&Report| {
+            p.edits()
+                .any(|a| report.edits().any(|b| overlaps(a.at, b.at)))
+        };
+        match picked.iter().find(conflicts) {
+            Some(blocker) => {
+                let code = blocker.code;
+                deferred.push((report, code))
+            }
+            None => picked.push(report),
+        }
+    }
+    (picked, deferred)
+}
 
-    reports
-        .into_iter()
-        .fold(VecDeque::new(), |mut deque: VecDeque<Report>, new_elem| {
-            let front = deque.front();
-            let new_range = new_elem.range();
-            if let Some(front_range) = front.map(|f| f.range()) {
-                if new_range.start() > front_range.end() {
-                    deque.push_front(new_elem);
-                }
+// fixes that merely touch can both be applied, unless both insert text
+// at the same position, the order of the insertions would be arbitrary
+fn overlaps(a: TextRange, b: TextRange) -> bool {
+    a == b || (a.start() < b.end() && b.start() < a.end())
+}
+
+/// Where an offset ends up once `edits` are applied, edits are given as
+/// the replaced range and the length of the replacement. Offsets within
+/// a replaced range move to the start or end of the replacement.
+fn map_offset(offset: TextSize, edits: &[(TextRange, TextSize)], is_end: bool) -> TextSize {
+    let mut mapped = i64::from(u32::from(offset));
+    for (at, len) in edits {
+        let delta = i64::from(u32::from(*len)) - i64::from(u32::from(at.len()));
+        if at.end() <= offset {
+            mapped += delta;
+        } else if at.start() < offset {
+            let start = mapped - i64::from(u32::from(offset - at.start()));
+            mapped = if is_end {
+                start + i64::from(u32::from(*len))
             } else {
-                deque.push_front(new_elem);
+                start
+            };
+        }
+    }
+    TextSize::from(mapped.max(0) as u32)
+}
+
+/// A fix that was not picked in a pass
+#[derive(Debug, Clone)]
+pub struct Deferred {
+    code: u32,
+    /// Where the fix would apply, in the source of the next pass
+    at: TextRange,
+    /// The text the fix would have replaced
+    text: String,
+    blocked_by: u32,
+}
+
+impl FixResult<'_> {
+    // deferred fixes whose lint no longer fires around the same place
+    // were superseded by the fix that was picked over them
+    fn settle_deferred(&mut self, reports: &[Report]) {
+        for deferred in std::mem::take(&mut self.deferred) {
+            let retried = reports
+                .iter()
+                .any(|r| r.code == deferred.code && r.range().intersect(deferred.at).is_some());
+            if !retried {
+                self.skipped.push(Skipped {
+                    code: deferred.code,
+                    text: deferred.text,
+                    blocked_by: deferred.blocked_by,
+                });
             }
-            deque
-        })
-        .into()
+        }
+    }
 }
 
 impl<'a> Iterator for FixResult<'a> {
     type Item = FixResult<'a>;
     fn next(&mut self) -> Option<Self::Item> {
-        let all_reports = collect_fixes(&self.src, self.lints, self.sess).ok()?;
-        if all_reports.is_empty() {
-            return None;
+        // a pass in which every picked fix is rolled back changes
+        // nothing, the next one goes without those fixes
+        loop {
+            let mut all_reports =
+                collect_fixes(&self.src, self.lints, self.sess, self.applicability).ok()?;
+            all_reports.retain(|r| !self.rejected.contains(&(r.code, r.range())));
+            self.settle_deferred(&all_reports);
+            if all_reports.is_empty() {
+                return None;
+            }
+
+            let (picked, deferred) = resolve(all_reports);
+            let (picked, rejected) = self.verify(picked);
+
+            let fixed = picked
+                .iter()
+                .map(|r| Fixed {
+                    at: r.range(),
+                    code: r.code,
+                })
+                .collect::<Vec<_>>();
+
+            // edits of different fixes may interleave, a fix may remove
+            // the parentheses around another fix, for example
+            let mut edits = picked.iter().flat_map(|r| r.edits()).collect::<Vec<_>>();
+            edits.sort_by_key(|e| e.at.start());
+            let lengths = edits
+                .iter()
+                .map(|e| (e.at, TextSize::of(e.fix.to_string().as_str())))
+                .collect::<Vec<_>>();
+            let map_range = |at: TextRange| {
+                TextRange::new(
+                    map_offset(at.start(), &lengths, false),
+                    map_offset(at.end(), &lengths, true),
+                )
+            };
+            self.deferred = deferred
+                .into_iter()
+                .map(|(report, blocked_by)| {
+                    let at = report.range();
+                    Deferred {
+                        code: report.code,
+                        at: map_range(at),
+                        text: self.src[at].to_owned(),
+                        blocked_by,
+                    }
+                })
+                .collect();
+            self.rejected = std::mem::take(&mut self.rejected)
+                .into_iter()
+                .chain(rejected)
+                .map(|(code, at)| (code, map_range(at)))
+                .collect();
+            if picked.is_empty() {
+                continue;
+            }
+
+            // apply from the end of the source, so that applying one edit
+            // does not shift the ranges of the rest
+            for edit in edits.into_iter().rev() {
+                edit.apply(self.src.to_mut());
+            }
+
+            return Some(FixResult {
+                src: self.src.clone(),
+                fixed,
+                skipped: Vec::new(),
+                rolled_back: Vec::new(),
+                deferred: Vec::new(),
+                rejected: Vec::new(),
+                lints: self.lints,
+                sess: self.sess,
+                applicability: self.applicability,
+            });
         }
+    }
+}
 
-        let reordered = reorder(all_reports);
-        let fixed = reordered
-            .iter()
-            .map(|r| Fixed {
-                at: r.range(),
-                code: r.code,
-            })
-            .collect::<Vec<_>>();
-        for report in reordered {
-            report.apply(self.src.to_mut());
+impl FixResult<'_> {
+    // picked fixes are checked together first, which is all it takes
+    // unless one of them does not hold up. Each fix is then checked on
+    // its own, fixes that do not hold up are recorded and left out.
+    // Fixes that hold up on their own could still clash once applied
+    // together, only the first is kept then, the rest are picked again
+    // in the next pass.
+    fn verify(&mut self, picked: Vec<Report>) -> (Vec<Report>, Vec<(u32, TextRange)>) {
+        let Self {
+            src, rolled_back, ..
+        } = self;
+        let original = verify::Original::new(src);
+        let holds_up = |reports: &[Report]| {
+            let fixes = reports
+                .iter()
+                .map(|r| r.edits().collect())
+                .collect::<Vec<_>>();
+            original.verify(&fixes).is_ok()
+        };
+        if holds_up(&picked) {
+            return (picked, Vec::new());
         }
 
-        Some(FixResult {
-            src: self.src.clone(),
-            fixed,
-            lints: self.lints,
-            sess: self.sess,
-        })
+        let mut verified = Vec::new();
+        let mut rejected = Vec::new();
+        for report in picked {
+            match original.verify(&[report.edits().collect()]) {
+                Ok(_) => verified.push(report),
+                Err(reason) => {
+                    let this = RolledBack {
+                        code: report.code,
+                        text: src[report.range()].to_owned(),
+                        reason,
+                    };
+                    if !rolled_back.contains(&this) {
+                        rolled_back.push(this);
+                    }
+                    rejected.push((report.code, report.range()));
+                }
+            }
+        }
+
+        if verified.len() > 1 && !holds_up(&verified) {
+            verified.truncate(1);
+        }
+        (verified, rejected)
     }
 }
 
+/// Fix passes are run until no fixes are left. Fixes of two lints may
+/// undo each other, passes that bring back an earlier source are caught
+/// with Brent's take on the hare and tortoise: the tortoise teleports to
+/// the hare at every power of two, so that only hashes of sources are
+/// kept around, and no pass is run twice.
 pub fn all_with<'a>(
     src: &'a str,
     lints: &'a LintMap,
     sess: &'a SessionInfo,
-) -> Option<FixResult<'a>> {
+    max_passes: usize,
+    applicability: Applicability,
+) -> Result<Option<FixResult<'a>>, FixErr> {
     let src = Cow::from(src);
-    let _ = rnix::parse(&src).as_result().ok()?;
-    let initial = FixResult::empty(src, lints, sess);
-    initial.into_iter().last()
+    if rnix::parse(&src).as_result().is_err() {
+        return Ok(None);
+    }
+
+    let mut tortoise = utils::hash(src.as_bytes());
+    let (mut power, mut cycle_len) = (1, 0);
+    // codes of lints fixed in each pass
+    let mut history: Vec<Vec<u32>> = Vec::new();
+    let mut fixed = Vec::new();
+
+    let mut passes = FixResult::empty(src, lints, sess, applicability);
+    for (pass, result) in passes.by_ref().enumerate() {
+        history.push(result.fixed.iter().map(|f| f.code).collect());
+        if pass == max_passes {
+            return Err(FixErr::NoConvergence(
+                max_passes,
+                describe(history.last().unwrap()),
+            ));
+        }
+
+        let hare = utils::hash(result.src.as_bytes());
+        cycle_len += 1;
+        if hare == tortoise {
+            let codes = history[history.len() - cycle_len..].concat();
+            return Err(FixErr::Cycle(describe(&codes)));
+        }
+        if power == cycle_len {
+            tortoise = hare;
+            power *= 2;
+            cycle_len = 0;
+        }
+        fixed.extend(result.fixed);
+    }
+    // rolled back fixes are reported even if nothing was fixed
+    if fixed.is_empty() && passes.rolled_back.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(FixResult { fixed, ..passes }))
+}
+
+// `W03 manual_inherit, W04 manual_inherit_from`
+fn describe(codes: &[u32]) -> String {
+    let mut codes = codes.to_vec();
+    codes.sort_unstable();
+    codes.dedup();
+    codes
+        .into_iter()
+        .map(|c| format!("{} {}", utils::code(c), utils::lint_name(c)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fix::Rejection;
+    use lib::{Category, Explain, Lint, Metadata, Rule, Suggestion};
+    use rnix::{SyntaxElement, SyntaxKind};
+
+    /// Rewrites nodes of a kind
+    struct Rewrite {
+        code: u32,
+        kind: SyntaxKind,
+        rewrite: fn(&str) -> Option<String>,
+    }
+
+    impl Metadata for Rewrite {
+        fn name(&self) -> &'static str {
+            "rewrite"
+        }
+        fn note(&self) -> &'static str {
+            "Rewrite"
+        }
+        fn code(&self) -> u32 {
+            self.code
+        }
+        fn category(&self) -> Category {
+            Category::Style
+        }
+        fn report(&self) -> Report {
+            Report::new(self.note(), self.code)
+        }
+        fn match_with(&self, with: &SyntaxKind) -> bool {
+            *with == self.kind
+        }
+        fn match_kind(&self) -> Vec<SyntaxKind> {
+            vec![self.kind]
+        }
+    }
+
+    impl Explain for Rewrite {}
+
+    impl Rule for Rewrite {
+        fn validate(&self, node: &SyntaxElement, _sess: &SessionInfo) -> Option<Report> {
+            let at = node.text_range();
+            let fix = (self.rewrite)(&node.to_string())?;
+            let fix = rnix::parse(&fix).node();
+            Some(
+                self.report()
+                    .suggest(at, "rewrite", Suggestion::new(at, fix)),
+            )
+        }
+    }
+
+    impl Lint for Rewrite {}
+
+    fn lint_map(lints: Vec<Rewrite>) -> LintMap {
+        let lints = lints
+            .into_iter()
+            .map(|lint| &*Box::leak(Box::new(Box::new(lint) as Box<dyn Lint>)))
+            .collect::<Vec<_>>();
+        utils::lint_map_of(&lints)
+    }
+
+    fn fix_result<'a>(
+        src: &'a str,
+        lints: &'a LintMap,
+        sess: &'a SessionInfo,
+    ) -> Result<Option<FixResult<'a>>, FixErr> {
+        all_with(src, lints, sess, 16, Applicability::Safe)
+    }
+
+    fn fix(src: &str, lints: &LintMap) -> Result<Option<String>, FixErr> {
+        let sess = SessionInfo::from_version("2.4".parse().unwrap());
+        fix_result(src, lints, &sess).map(|r| r.map(|r| r.src.into_owned()))
+    }
+
+    #[test]
+    fn settles() {
+        let lints = lint_map(vec![Rewrite {
+            code: 101,
+            kind: SyntaxKind::NODE_IDENT,
+            rewrite: |s| (s == "a").then(|| "b".into()),
+        }]);
+        assert_eq!(fix("[ a a ]", &lints).unwrap().as_deref(), Some("[ b b ]"));
+        assert_eq!(fix("[ c ]", &lints).unwrap(), None);
+    }
+
+    #[test]
+    fn cycle() {
+        let lints = lint_map(vec![
+            Rewrite {
+                code: 101,
+                kind: SyntaxKind::NODE_IDENT,
+                rewrite: |s| (s == "a").then(|| "b".into()),
+            },
+            Rewrite {
+                code: 102,
+                kind: SyntaxKind::NODE_IDENT,
+                rewrite: |s| (s == "b").then(|| "a".into()),
+            },
+        ]);
+        match fix("[ a ]", &lints) {
+            Err(FixErr::Cycle(lints)) => {
+                assert!(lints.contains("W101"));
+                assert!(lints.contains("W102"));
+            }
+            _ => panic!("expected a cycle"),
+        }
+    }
+
+    #[test]
+    fn no_op_fix() {
+        let lints = lint_map(vec![Rewrite {
+            code: 101,
+            kind: SyntaxKind::NODE_IDENT,
+            rewrite: |s| Some(s.into()),
+        }]);
+        assert!(matches!(fix("[ a ]", &lints), Err(FixErr::Cycle(_))));
+    }
+
+    #[test]
+    fn no_convergence() {
+        let lints = lint_map(vec![Rewrite {
+            code: 101,
+            kind: SyntaxKind::NODE_IDENT,
+            rewrite: |s| Some(format!("{}a", s)),
+        }]);
+        assert!(matches!(
+            fix("[ a ]", &lints),
+            Err(FixErr::NoConvergence(16, _))
+        ));
+    }
+
+    #[test]
+    fn nested_fixes_come_first() {
+        let lints = lint_map(vec![
+            Rewrite {
+                code: 101,
+                kind: SyntaxKind::NODE_IDENT,
+                rewrite: |s| (s == "a").then(|| "b".into()),
+            },
+            Rewrite {
+                code: 102,
+                kind: SyntaxKind::NODE_LIST,
+                rewrite: |s| (s == "[ b ]").then(|| "[ c ]".into()),
+            },
+        ]);
+        // the list fix only applies once the nested fix is made
+        assert_eq!(fix("[ a ]", &lints).unwrap().as_deref(), Some("[ c ]"));
+    }
+
+    #[test]
+    fn superseded_fixes_are_skipped() {
+        let lints = lint_map(vec![
+            Rewrite {
+                code: 101,
+                kind: SyntaxKind::NODE_IDENT,
+                rewrite: |s| (s == "a").then(|| "b".into()),
+            },
+            Rewrite {
+                code: 102,
+                kind: SyntaxKind::NODE_LIST,
+                rewrite: |s| (s == "[ a ]").then(|| "[ c ]".into()),
+            },
+        ]);
+        let sess = SessionInfo::from_version("2.4".parse().unwrap());
+        let result = fix_result("[ a ] ++ [ d ]", &lints, &sess)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.src, "[ b ] ++ [ d ]");
+        assert_eq!(result.fixed.len(), 1);
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].code, 102);
+        assert_eq!(result.skipped[0].text, "[ a ]");
+        assert_eq!(result.skipped[0].blocked_by, 101);
+    }
+
+    #[test]
+    fn interleaved_edits() {
+        let useless_parens = lib::LINTS
+            .iter()
+            .find(|l| l.name() == "useless_parens")
+            .unwrap();
+        let rewrite = Box::leak(Box::new(Box::new(Rewrite {
+            code: 101,
+            kind: SyntaxKind::NODE_IDENT,
+            rewrite: |s| (s == "a").then(|| "b".into()),
+        }) as Box<dyn Lint>));
+        let lints = utils::lint_map_of(&[useless_parens, rewrite]);
+        let sess = SessionInfo::from_version("2.4".parse().unwrap());
+        let result = fix_result("[ (a) ]", &lints, &sess).unwrap().unwrap();
+        // the parentheses are deleted around the rewritten identifier,
+        // neither fix is skipped
+        assert_eq!(result.src, "[ b ]");
+        assert_eq!(result.fixed.len(), 2);
+        assert!(result.skipped.is_empty());
+    }
+
+    #[test]
+    fn unsafe_fixes_are_opt_in() {
+        let eta_reduction = lib::LINTS
+            .iter()
+            .find(|l| l.name() == "eta_reduction")
+            .unwrap();
+        let lints = utils::lint_map_of(&[eta_reduction]);
+        let sess = SessionInfo::from_version("2.4".parse().unwrap());
+        let fix = |applicability| {
+            all_with("x: f x", &lints, &sess, 16, applicability)
+                .unwrap()
+                .map(|r| r.src.into_owned())
+        };
+        assert_eq!(fix(Applicability::Safe), None);
+        assert_eq!(fix(Applicability::Unsafe).as_deref(), Some("f"));
+    }
+
+    #[test]
+    fn unused_plain_argument() {
+        let unused_argument = lib::LINTS
+            .iter()
+            .find(|l| l.name() == "unused_argument")
+            .unwrap();
+        let lints = utils::lint_map_of(&[unused_argument]);
+        let sess = SessionInfo::from_version("2.4".parse().unwrap());
+        let fix = |applicability| {
+            all_with("x: 0", &lints, &sess, 16, applicability)
+                .unwrap()
+                .map(|r| r.src.into_owned())
+        };
+        assert_eq!(fix(Applicability::Safe), None);
+        assert_eq!(fix(Applicability::Unsafe).as_deref(), Some("_: 0"));
+    }
+
+    #[test]
+    fn bad_fixes_are_rolled_back() {
+        let lints = lint_map(vec![
+            Rewrite {
+                code: 101,
+                kind: SyntaxKind::NODE_IDENT,
+                rewrite: |s| (s == "y").then(|| "a + b".into()),
+            },
+            Rewrite {
+                code: 102,
+                kind: SyntaxKind::NODE_IDENT,
+                rewrite: |s| (s == "z").then(|| "c".into()),
+            },
+        ]);
+        let sess = SessionInfo::from_version("2.4".parse().unwrap());
+        let result = fix_result("[ (x * y) z ]", &lints, &sess).unwrap().unwrap();
+        assert_eq!(result.src, "[ (x * y) c ]");
+        assert_eq!(result.fixed.len(), 1);
+        assert_eq!(result.rolled_back.len(), 1);
+        assert_eq!(result.rolled_back[0].code, 101);
+        assert_eq!(result.rolled_back[0].reason, Rejection::OutsideRange);
+
+        // nothing is fixed, but the rolled back fix is reported
+        let result = fix_result("x * y", &lints, &sess).unwrap().unwrap();
+        assert_eq!(result.src, "x * y");
+        assert!(result.fixed.is_empty());
+        assert_eq!(result.rolled_back.len(), 1);
+    }
+
+    #[test]
+    fn offsets_through_edits() {
+        let range = |start: u32, end: u32| TextRange::new(start.into(), end.into());
+        // `aa bb cc` -> `aa b cc`
+        let edits = [(range(3, 5), TextSize::from(1))];
+        assert_eq!(map_offset(1.into(), &edits, false), TextSize::from(1));
+        assert_eq!(map_offset(6.into(), &edits, false), TextSize::from(5));
+        assert_eq!(map_offset(4.into(), &edits, false), TextSize::from(3));
+        assert_eq!(map_offset(4.into(), &edits, true), TextSize::from(4));
+    }
 }
@@ -1,14 +1,26 @@
 use std::{borrow::Cow, convert::TryFrom};
 
-use lib::{session::SessionInfo, Report};
-use rnix::{TextSize, WalkEvent};
+use lib::{session::SessionInfo, suppression::Suppressions, Applicability, Diagnostic};
+use rnix::{TextRange, TextSize, WalkEvent};
 
-use crate::{err::SingleFixErr, fix::Source, utils};
+use crate::{
+    config::Selection,
+    err::SingleFixErr,
+    fix::{applicable, verify::verify, Source},
+    utils, LintMap,
+};
 
 pub struct SingleFixResult<'δ> {
     pub src: Source<'δ>,
 }
 
+/// A fix available within the selection
+pub struct Candidate {
+    pub code: u32,
+    /// The diagnostic the fix belongs to, it always has a suggestion
+    pub diagnostic: Diagnostic,
+}
+
 fn pos_to_byte(line: usize, col: usize, src: &str) -> Result<TextSize, SingleFixErr> {
     let mut byte: TextSize = TextSize::of("");
     for (l, _) in src
@@ -27,39 +39,162 @@ fn pos_to_byte(line: usize, col: usize, src: &str) -> Result<TextSize, SingleFix
     }
 }
 
-fn find(offset: TextSize, src: &str, sess: &SessionInfo) -> Result<Report, SingleFixErr> {
+/// The inverse of `pos_to_byte`: lines start at 1, columns at 0
+pub fn byte_to_pos(at: TextSize, src: &str) -> (usize, usize) {
+    let at = usize::from(at);
+    let line_start = src[..at].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (src[..at].matches('\n').count() + 1, at - line_start)
+}
+
+fn to_range(selection: &Selection, src: &str) -> Result<TextRange, SingleFixErr> {
+    let (line, col) = selection.start;
+    let start = pos_to_byte(line, col, src)?;
+    let (line, col) = selection.end;
+    let end = pos_to_byte(line, col, src)?;
+    if start <= end {
+        Ok(TextRange::new(start, end))
+    } else {
+        Err(SingleFixErr::OutOfBounds(line, col))
+    }
+}
+
+/// Fixes whose diagnostics contain the cursor, or overlap the
+/// selection, outermost fixes come first
+pub fn candidates(
+    selection: &Selection,
+    src: &str,
+    lints: &LintMap,
+    sess: &SessionInfo,
+    applicability: Applicability,
+) -> Result<Vec<Candidate>, SingleFixErr> {
+    let selection = to_range(selection, src)?;
+    let hit = |at: TextRange| {
+        if selection.is_empty() {
+            at.contains(selection.start())
+        } else {
+            at.intersect(selection).is_some_and(|r| !r.is_empty())
+        }
+    };
+
     // we don't really need the source to form a completely parsed tree
     let parsed = rnix::parse(src);
-    let lints = utils::lint_map();
+    let sess = sess.with_scopes(&parsed.node());
 
-    parsed
+    let reports = parsed
         .node()
         .preorder_with_tokens()
         .filter_map(|event| match event {
             WalkEvent::Enter(child) => lints.get(&child.kind()).map(|rules| {
                 rules
                     .iter()
-                    .filter_map(|rule| rule.validate(&child, sess))
-                    .find(|report| report.total_suggestion_range().is_some())
+                    .filter_map(|rule| rule.check(&child, &sess))
+                    .collect::<Vec<_>>()
             }),
             _ => None,
         })
-        .flatten()
-        .find(|report| report.total_diagnostic_range().unwrap().contains(offset))
-        .ok_or(SingleFixErr::NoOp)
+        .flatten();
+
+    Ok(Suppressions::new(&parsed.node())
+        .apply(reports)
+        .into_iter()
+        .map(|report| applicable(report, applicability))
+        .flat_map(|report| {
+            let code = report.code;
+            report
+                .diagnostics
+                .into_iter()
+                .filter(|d| d.suggestion.is_some() && hit(d.at))
+                .map(move |diagnostic| Candidate { code, diagnostic })
+        })
+        .collect())
 }
 
+/// Apply the first fix within the selection, only fixes of the lint
+/// with this `code` are considered if one is given
 pub fn single<'a>(
-    line: usize,
-    col: usize,
+    selection: &Selection,
+    code: Option<u32>,
     src: &'a str,
+    lints: &LintMap,
     sess: &SessionInfo,
+    applicability: Applicability,
 ) -> Result<SingleFixResult<'a>, SingleFixErr> {
-    let mut src = Cow::from(src);
-    let offset = pos_to_byte(line, col, &src)?;
-    let report = find(offset, &src, sess)?;
+    let candidate = candidates(selection, src, lints, sess, applicability)?
+        .into_iter()
+        .find(|c| code.is_none_or(|code| c.code == code))
+        .ok_or(SingleFixErr::NoOp)?;
 
-    report.apply(src.to_mut());
+    let edits = candidate
+        .diagnostic
+        .suggestion
+        .iter()
+        .flat_map(|s| s.edits.iter())
+        .collect::<Vec<_>>();
+    let fixed = verify(src, &edits).map_err(|reason| {
+        let lint = format!(
+            "{} {}",
+            utils::code(candidate.code),
+            utils::lint_name(candidate.code)
+        );
+        SingleFixErr::RolledBack(lint, reason)
+    })?;
 
-    Ok(SingleFixResult { src })
+    Ok(SingleFixResult {
+        src: Cow::Owned(fixed),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(position: &str, code: Option<u32>, src: &str) -> Result<String, SingleFixErr> {
+        let sess = SessionInfo::from_version("2.4".parse().unwrap());
+        let lints = crate::utils::lint_map();
+        single(
+            &position.parse().unwrap(),
+            code,
+            src,
+            &lints,
+            &sess,
+            Applicability::Safe,
+        )
+        .map(|r| r.src.into_owned())
+    }
+
+    #[test]
+    fn outermost_first() {
+        let src = "{ a = ((x)); }\n";
+        assert_eq!(fix("1,8", None, src).unwrap(), "{ a = (x); }\n");
+    }
+
+    #[test]
+    fn selection() {
+        let src = "{ a = (x); b = ({ }); }\n";
+        assert_eq!(
+            fix("1,0-1,8", None, src).unwrap(),
+            "{ a = x; b = ({ }); }\n"
+        );
+        assert_eq!(
+            fix("1,15-1,16", None, src).unwrap(),
+            "{ a = (x); b = { }; }\n"
+        );
+        assert!(matches!(fix("1,0-1,2", None, src), Err(SingleFixErr::NoOp)));
+    }
+
+    #[test]
+    fn by_code() {
+        let src = "{ a = ((x)); }\n";
+        assert!(matches!(fix("1,8", Some(7), src), Err(SingleFixErr::NoOp)));
+    }
+
+    #[test]
+    fn positions_roundtrip() {
+        let src = "a\nbc\nd\n";
+        for at in 0..src.len() {
+            let at = TextSize::try_from(at).unwrap();
+            let (line, col) = byte_to_pos(at, src);
+            assert_eq!(pos_to_byte(line, col, src).unwrap(), at);
+        }
+    }
 }
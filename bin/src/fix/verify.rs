@@ -0,0 +1,239 @@
+//! Checks that a fix does what it claims to.
+//!
+//! Fixes are built out of templates, a template that forgets to
+//! parenthesize, for example, produces text that parses differently
+//! once placed in the source. Each fix is applied to a copy of the
+//! source and parsed again: the fix must not introduce parse errors,
+//! and the tree outside of the node it targets must stay the same.
+//! The fixes of a pass are checked together, with a single parse of
+//! the fixed source, each one against the node it targets.
+
+use std::{collections::HashSet, fmt};
+
+use lib::Edit;
+use rnix::{NodeOrToken, SyntaxKind, SyntaxNode, TextRange, TextSize};
+
+/// Why a fix was rolled back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    /// The fixed source does not parse
+    ParseError(String),
+    /// The fixed source parses, but code around the fix means
+    /// something else now
+    OutsideRange,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParseError(e) => write!(f, "it introduces a syntax error: {}", e),
+            Self::OutsideRange => write!(f, "it changes code outside of its range"),
+        }
+    }
+}
+
+/// Apply `edits` to a copy of `src`, the fixed source is returned if
+/// the fix holds up
+pub fn verify(src: &str, edits: &[&Edit]) -> Result<String, Rejection> {
+    Original::new(src).verify(&[edits.to_vec()])
+}
+
+/// A source along with its tree, parsed once for every set of fixes
+/// that is checked against it
+pub struct Original<'a> {
+    src: &'a str,
+    root: SyntaxNode,
+    errors: usize,
+}
+
+impl<'a> Original<'a> {
+    pub fn new(src: &'a str) -> Self {
+        let parsed = rnix::parse(src);
+        Self {
+            src,
+            root: parsed.node(),
+            errors: parsed.errors().len(),
+        }
+    }
+
+    /// Apply the edits of several fixes to a copy of the source, the
+    /// fixed source is returned if every fix holds up. Each fix is
+    /// held to the node it targets, as if it were applied on its own,
+    /// fixes that target nested nodes are rejected together.
+    pub fn verify(&self, fixes: &[Vec<&Edit>]) -> Result<String, Rejection> {
+        let mut fixed = self.src.to_owned();
+        let mut edits = fixes.iter().flatten().collect::<Vec<_>>();
+        edits.sort_by_key(|e| std::cmp::Reverse(e.at.start()));
+        for edit in edits {
+            edit.apply(&mut fixed);
+        }
+
+        let new = rnix::parse(&fixed);
+        // the source need not parse to begin with, `statix single` works
+        // on files that are being edited
+        if new.errors().len() > self.errors {
+            let error = new.errors().into_iter().next().unwrap();
+            return Err(Rejection::ParseError(error.to_string()));
+        }
+
+        // the node each fix targets, along with how much the fix grows it
+        let mut holes = fixes
+            .iter()
+            .filter_map(|edits| {
+                let at = edits
+                    .iter()
+                    .map(|e| e.at)
+                    .reduce(|acc, next| acc.cover(next))?;
+                let hole = match self.root.covering_element(at) {
+                    NodeOrToken::Node(node) => node,
+                    NodeOrToken::Token(token) => token.parent(),
+                };
+                let delta = edits
+                    .iter()
+                    .map(|e| e.fix.to_string().len() as i64 - u32::from(e.at.len()) as i64)
+                    .sum::<i64>();
+                Some((hole, delta))
+            })
+            .collect::<Vec<_>>();
+        holes.sort_by_key(|(hole, _)| hole.text_range().start());
+        let old_holes = holes
+            .iter()
+            .map(|(hole, _)| hole.clone())
+            .collect::<HashSet<_>>();
+        let nested = holes
+            .iter()
+            .any(|(hole, _)| hole.ancestors().skip(1).any(|a| old_holes.contains(&a)));
+        if nested || old_holes.len() < holes.len() {
+            return Err(Rejection::OutsideRange);
+        }
+
+        // the nodes at the same places in the new tree, they span the fixes
+        let new_root = new.node();
+        let mut shift = 0;
+        let mut new_holes = HashSet::new();
+        for (old_hole, delta) in &holes {
+            let new_hole = path(old_hole)
+                .into_iter()
+                .try_fold(new_root.clone(), |node, index| node.children().nth(index))
+                .ok_or(Rejection::OutsideRange)?;
+            let old_range = old_hole.text_range();
+            let start = u32::from(old_range.start()) as i64 + shift;
+            let len = u32::from(old_range.len()) as i64 + delta;
+            let expected = TextRange::at(
+                TextSize::from(start.max(0) as u32),
+                TextSize::from(len.max(0) as u32),
+            );
+            if new_hole.text_range() != expected {
+                return Err(Rejection::OutsideRange);
+            }
+            shift += delta;
+            new_holes.insert(new_hole);
+        }
+        if skeleton(&self.root, &old_holes) != skeleton(&new_root, &new_holes) {
+            return Err(Rejection::OutsideRange);
+        }
+        Ok(fixed)
+    }
+}
+
+// indices of the node and its ancestors among the child nodes of
+// their parents, from the root down
+fn path(node: &SyntaxNode) -> Vec<usize> {
+    let mut path = node
+        .ancestors()
+        .filter_map(|n| {
+            let parent = n.parent()?;
+            parent.children().position(|c| c == n)
+        })
+        .collect::<Vec<_>>();
+    path.reverse();
+    path
+}
+
+// kinds of nodes and tokens, along with the text of tokens, in
+// preorder, the subtrees of `holes` are left out
+fn skeleton(root: &SyntaxNode, holes: &HashSet<SyntaxNode>) -> Vec<(SyntaxKind, Option<String>)> {
+    fn walk(
+        node: &SyntaxNode,
+        holes: &HashSet<SyntaxNode>,
+        out: &mut Vec<(SyntaxKind, Option<String>)>,
+    ) {
+        // error tokens always carry text, one without any stands in
+        // for a hole
+        if holes.contains(node) {
+            out.push((SyntaxKind::TOKEN_ERROR, None));
+            return;
+        }
+        out.push((node.kind(), None));
+        for child in node.children_with_tokens() {
+            match child {
+                NodeOrToken::Node(child) => walk(&child, holes, out),
+                NodeOrToken::Token(token) => {
+                    out.push((token.kind(), Some(token.text().to_owned())))
+                }
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(root, holes, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace(src: &str, at: (u32, u32), fix: &str) -> Result<String, Rejection> {
+        let at = TextRange::new(at.0.into(), at.1.into());
+        let fix = rnix::parse(fix).node();
+        verify(src, &[&Edit::new(at, fix)])
+    }
+
+    #[test]
+    fn good_fix() {
+        assert_eq!(replace("x * y", (4, 5), "(a + b)").unwrap(), "x * (a + b)");
+        assert_eq!(replace("[ (x) ]", (2, 5), "x").unwrap(), "[ x ]");
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            replace("x * y", (4, 5), "a + b"),
+            Err(Rejection::OutsideRange)
+        );
+    }
+
+    #[test]
+    fn several_fixes() {
+        let edit = |at: (u32, u32), fix: &str| {
+            Edit::new(
+                TextRange::new(at.0.into(), at.1.into()),
+                rnix::parse(fix).node(),
+            )
+        };
+        let original = Original::new("[ (x * y) z ]");
+        let (y, z) = (edit((7, 8), "a + b"), edit((10, 11), "c"));
+        assert_eq!(original.verify(&[vec![&z]]).unwrap(), "[ (x * y) c ]");
+        // each fix is held to its own node, not to the list around both
+        assert_eq!(
+            original.verify(&[vec![&y], vec![&z]]),
+            Err(Rejection::OutsideRange)
+        );
+
+        let (x, paren) = (edit((3, 4), "w"), edit((2, 9), "(x * v)"));
+        assert!(original.verify(&[vec![&x]]).is_ok());
+        assert!(original.verify(&[vec![&paren]]).is_ok());
+        assert_eq!(
+            original.verify(&[vec![&x], vec![&paren]]),
+            Err(Rejection::OutsideRange)
+        );
+    }
+
+    #[test]
+    fn syntax_error() {
+        assert!(matches!(
+            replace("[ x ]", (4, 5), "["),
+            Err(Rejection::ParseError(_))
+        ));
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(feature = "json")]
+pub mod baseline;
+pub mod cache;
 pub mod config;
 pub mod dirs;
 pub mod dump;
@@ -6,14 +9,61 @@ pub mod explain;
 pub mod fix;
 pub mod lint;
 pub mod list;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod profile;
 pub mod session;
 pub mod traits;
+pub mod watch;
 
 mod utils;
 
 use std::collections::HashMap;
 
-use lib::Lint;
+use lib::{Lint, Report, Severity};
 use rnix::SyntaxKind;
 
-pub type LintMap = HashMap<SyntaxKind, Vec<&'static Box<dyn Lint>>>;
+/// Enabled lints, keyed by the syntax kinds they match with, along
+/// with per-lint severity overrides
+#[derive(Default, Clone)]
+#[allow(clippy::borrowed_box)]
+pub struct LintMap {
+    map: HashMap<SyntaxKind, Vec<&'static Box<dyn Lint>>>,
+    severities: HashMap<u32, Severity>,
+}
+
+impl LintMap {
+    #[allow(clippy::borrowed_box)]
+    pub fn get(&self, kind: &SyntaxKind) -> Option<&Vec<&'static Box<dyn Lint>>> {
+        self.map.get(kind)
+    }
+    #[allow(clippy::borrowed_box)]
+    pub fn values(&self) -> impl Iterator<Item = &Vec<&'static Box<dyn Lint>>> {
+        self.map.values()
+    }
+    /// Override the severity of reports produced by the lint with this code
+    pub fn set_severity(&mut self, code: u32, severity: Severity) {
+        self.severities.insert(code, severity);
+    }
+    /// Enabled lints and severity overrides in a stable order, two maps
+    /// with the same fingerprint produce the same reports
+    pub fn fingerprint(&self) -> String {
+        let mut codes = self
+            .values()
+            .flatten()
+            .map(|l| l.code())
+            .collect::<Vec<_>>();
+        codes.sort_unstable();
+        codes.dedup();
+        let mut severities = self.severities.iter().collect::<Vec<_>>();
+        severities.sort_by_key(|(code, _)| **code);
+        format!("{:?} {:?}", codes, severities)
+    }
+    /// Apply severity overrides, if any, to a report
+    pub fn with_severity(&self, report: Report) -> Report {
+        match self.severities.get(&report.code) {
+            Some(severity) => report.severity(*severity),
+            None => report,
+        }
+    }
+}
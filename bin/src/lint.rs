@@ -1,6 +1,6 @@
-use crate::{utils, LintMap};
+use crate::{cache::Cache, utils, LintMap};
 
-use lib::{session::SessionInfo, Report};
+use lib::{session::SessionInfo, suppression::Suppressions, Report};
 use rnix::WalkEvent;
 use vfs::{FileId, VfsEntry};
 
@@ -14,67 +14,291 @@ pub fn lint_with(vfs_entry: VfsEntry, lints: &LintMap, sess: &SessionInfo) -> Li
     let file_id = vfs_entry.file_id;
     let source = vfs_entry.contents;
     let parsed = rnix::parse(source);
+    let sess = sess.with_scopes(&parsed.node());
 
     let error_reports = parsed.errors().into_iter().map(Report::from_parse_err);
-    let reports = parsed
+    let lint_reports = parsed
         .node()
         .preorder_with_tokens()
         .filter_map(|event| match event {
             WalkEvent::Enter(child) => lints.get(&child.kind()).map(|rules| {
                 rules
                     .iter()
-                    .filter_map(|rule| rule.validate(&child, sess))
+                    .filter_map(|rule| rule.check(&child, &sess))
+                    .map(|report| lints.with_severity(report))
                     .collect::<Vec<_>>()
             }),
             _ => None,
         })
-        .flatten()
-        .chain(error_reports)
-        .collect();
+        .flatten();
+
+    let mut suppressions = Suppressions::new(&parsed.node());
+    let mut reports = suppressions.apply(lint_reports);
+    let enabled = |code| lints.values().flatten().any(|l| l.code() == code);
+    reports.extend(
+        suppressions
+            .unused(enabled)
+            .into_iter()
+            .map(|report| lints.with_severity(report)),
+    );
+    reports.extend(error_reports);
 
     LintResult { file_id, reports }
 }
 
+/// Like `lint_with`, but files that were linted before with the same
+/// contents and config are not linted again
+pub fn lint_cached(
+    vfs_entry: VfsEntry,
+    lints: &LintMap,
+    sess: &SessionInfo,
+    cache: Option<&Cache>,
+) -> LintResult {
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return lint_with(vfs_entry, lints, sess),
+    };
+    let contents = vfs_entry.contents;
+    if let Some(reports) = cache.get(contents) {
+        return LintResult {
+            file_id: vfs_entry.file_id,
+            reports,
+        };
+    }
+    let result = lint_with(vfs_entry, lints, sess);
+    cache.insert(contents, &result.reports);
+    result
+}
+
 pub fn lint(vfs_entry: VfsEntry, sess: &SessionInfo) -> LintResult {
     lint_with(vfs_entry, &utils::lint_map(), sess)
 }
 
 pub mod main {
-    use std::io;
+    use std::{collections::HashSet, fs, io};
 
-    use super::lint_with;
+    use super::{lint_cached, LintResult};
+    #[cfg(feature = "json")]
+    use crate::baseline::Baseline;
     use crate::{
         config::{Check as CheckConfig, ConfFile},
-        err::StatixErr,
+        err::{ConfigErr, StatixErr, WatchErr},
+        profile::Profiles,
         traits::WriteDiagnostic,
+        watch::{self, Watcher},
     };
 
-    use lib::session::SessionInfo;
+    use lib::Severity;
     use rayon::prelude::*;
+    use vfs::VfsEntry;
+
+    /// What files are checked with: the profile of each file, the
+    /// cache, and the baseline of reports to leave out
+    struct Checker<'a> {
+        check_config: &'a CheckConfig,
+        profiles: Profiles,
+        #[cfg(feature = "json")]
+        baseline: Option<Baseline>,
+    }
+
+    impl<'a> Checker<'a> {
+        fn new(check_config: &'a CheckConfig) -> Result<Self, StatixErr> {
+            let conf_file = ConfFile::discover(check_config.conf_path())?;
+            Ok(Self {
+                check_config,
+                profiles: check_config.profiles(conf_file)?,
+                #[cfg(feature = "json")]
+                baseline: check_config.baseline()?,
+            })
+        }
+
+        fn lint(&self, vfs_entry: VfsEntry) -> Result<LintResult, ConfigErr> {
+            let profile = self.profiles.get(vfs_entry.file_path)?;
+            let cache = self.check_config.cache(&profile);
+            let (lints, session) = (&profile.lints, &profile.session);
+            #[cfg(feature = "json")]
+            if let Some(baseline) = &self.baseline {
+                let (file_path, contents) = (vfs_entry.file_path, vfs_entry.contents);
+                let result = lint_cached(vfs_entry, lints, session, cache);
+                return Ok(baseline.filter(result, file_path, contents));
+            }
+            Ok(lint_cached(vfs_entry, lints, session, cache))
+        }
+    }
 
     pub fn main(check_config: CheckConfig) -> Result<(), StatixErr> {
-        let conf_file = ConfFile::discover(&check_config.conf_path)?;
-        let lints = conf_file.lints();
-        let version = conf_file.version()?;
-        let session = SessionInfo::from_version(version);
+        if check_config.watch {
+            return watch(check_config);
+        }
 
-        let vfs = check_config.vfs(conf_file.ignore.as_slice())?;
+        let checker = Checker::new(&check_config)?;
+        let vfs = check_config.vfs(&check_config.ignore(&checker.profiles)?)?;
 
         let mut stdout = io::stdout();
-        let lint = |vfs_entry| lint_with(vfs_entry, &lints, &session);
-        let results = vfs
+        let mut results = vfs
             .par_iter()
-            .map(lint)
-            .filter(|lr| !lr.reports.is_empty())
-            .collect::<Vec<_>>();
+            .map(|entry| checker.lint(entry))
+            .collect::<Result<Vec<_>, _>>()?;
+        results.retain(|lr| !lr.reports.is_empty());
+        check_config.prune_cache();
+
+        #[cfg(feature = "json")]
+        if let Some(path) = &check_config.write_baseline {
+            let baseline = Baseline::from_results(&results, &vfs);
+            baseline.write(path)?;
+            eprintln!(
+                "Recorded {} diagnostics in {}",
+                baseline.len(),
+                path.display()
+            );
+            return Ok(());
+        }
+
+        stdout
+            .write_results(&results, &vfs, check_config.format())
+            .unwrap();
 
-        if !results.is_empty() {
-            for r in &results {
-                stdout.write(r, &vfs, check_config.format).unwrap();
+        std::process::exit(exit_code(&results));
+    }
+
+    /// 2 if errors were found, syntax errors included, 1 if warnings
+    /// were found, and 0 if only hints were found
+    pub(super) fn exit_code(results: &[LintResult]) -> i32 {
+        results
+            .iter()
+            .flat_map(|r| r.reports.iter())
+            .map(|r| match r.severity {
+                Severity::Hint => 0,
+                Severity::Warn => 1,
+                Severity::Error => 2,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Check every file, then check files again as they change. Only
+    /// files that changed are checked again, unless the config or the
+    /// ignore file changed, in which case everything is reloaded.
+    fn watch(check_config: CheckConfig) -> Result<(), StatixErr> {
+        let mut stdout = io::stdout();
+        let format = check_config.format();
+        let target = check_config.target();
+        loop {
+            let checker = Checker::new(&check_config)?;
+            let ignore = check_config.ignore(&checker.profiles)?;
+            let mut vfs = check_config.vfs(&ignore)?;
+
+            let mut watcher = Watcher::new().map_err(WatchErr::from)?;
+            watcher
+                .watch_target(target, check_config.conf_path(), &ignore)
+                .map_err(WatchErr::from)?;
+
+            let mut results = vfs
+                .par_iter()
+                .map(|entry| checker.lint(entry))
+                .collect::<Result<Vec<_>, _>>()?;
+            results.retain(|lr| !lr.reports.is_empty());
+            check_config.prune_cache();
+
+            loop {
+                results.sort_by(|a, b| vfs.file_path(a.file_id).cmp(vfs.file_path(b.file_id)));
+                stdout.clear(format).map_err(WatchErr::from)?;
+                stdout
+                    .write_results(&results, &vfs, format)
+                    .map_err(WatchErr::from)?;
+                eprintln!("Watching {} for changes", target.display());
+
+                // config files are read as the files they apply to come up
+                let conf_files = checker.profiles.conf_files();
+                watcher
+                    .watch_conf_files(&conf_files)
+                    .map_err(WatchErr::from)?;
+                let changes = watcher.wait().map_err(WatchErr::from)?;
+                if watch::needs_reload(&changes, &conf_files) {
+                    break;
+                }
+                let (modified, removed) = watch::nix_files(&mut watcher, changes, target, &ignore)
+                    .map_err(WatchErr::from)?;
+
+                let mut changed = HashSet::new();
+                for path in removed {
+                    // removed paths may be directories
+                    let gone = vfs
+                        .iter()
+                        .filter(|entry| entry.file_path.starts_with(&path))
+                        .map(|entry| (entry.file_id, entry.file_path.to_owned()))
+                        .collect::<Vec<_>>();
+                    for (file_id, file_path) in gone {
+                        vfs.remove_file(file_path);
+                        changed.insert(file_id);
+                    }
+                }
+                let mut relint = Vec::new();
+                for path in modified {
+                    let file_id = vfs.alloc_file_id(&path);
+                    changed.insert(file_id);
+                    // the file may be gone again by the time it is read
+                    match fs::read_to_string(&path) {
+                        Ok(src) => {
+                            vfs.set_file_contents(&path, src.as_bytes());
+                            relint.push(file_id);
+                        }
+                        Err(_) => {
+                            vfs.remove_file(&path);
+                        }
+                    }
+                }
+
+                results.retain(|lr| !changed.contains(&lr.file_id));
+                for file_id in relint {
+                    let result = checker.lint(VfsEntry {
+                        file_id,
+                        file_path: vfs.file_path(file_id),
+                        contents: vfs.get_str(file_id),
+                    })?;
+                    if !result.reports.is_empty() {
+                        results.push(result);
+                    }
+                }
             }
-            std::process::exit(1);
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use lib::Severity;
+    use vfs::FileId;
+
+    #[test]
+    fn exit_code() {
+        let sess = SessionInfo::from_version("2.4".parse().unwrap());
+        let result = |severity| LintResult {
+            file_id: FileId(0),
+            reports: vec![Report::new("note", 1).severity(severity)],
+        };
+        let exit_code = main::exit_code;
+
+        assert_eq!(exit_code(&[]), 0);
+        assert_eq!(exit_code(&[result(Severity::Hint)]), 0);
+        assert_eq!(exit_code(&[result(Severity::Warn)]), 1);
+        assert_eq!(exit_code(&[result(Severity::Error)]), 2);
+        assert_eq!(
+            exit_code(&[result(Severity::Warn), result(Severity::Error)]),
+            2
+        );
 
-        std::process::exit(0);
+        let syntax_error = lint(
+            VfsEntry {
+                file_id: FileId(0),
+                file_path: std::path::Path::new("a.nix"),
+                contents: "{ a = ; }",
+            },
+            &sess,
+        );
+        assert!(syntax_error.reports.iter().any(|r| r.code == 0));
+        assert_eq!(exit_code(&[syntax_error]), 2);
     }
 }
@@ -7,7 +7,12 @@ pub mod main {
         let mut lints = (*LINTS).clone();
         lints.as_mut_slice().sort_by_key(|a| a.code());
         for l in lints {
-            println!("W{:02} {}", l.code(), l.name());
+            println!(
+                "W{:02} {:<24} {}",
+                l.code(),
+                l.name(),
+                l.category().as_str()
+            );
         }
         Ok(())
     }
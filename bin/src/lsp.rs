@@ -0,0 +1,596 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::ConfFile,
+    err::{ConfigErr, LspErr},
+    explain,
+    lint::lint_with,
+    profile::Profiles,
+};
+
+mod protocol;
+use protocol::{
+    CodeAction, CodeActionParams, Diagnostic, DidChangeTextDocumentParams,
+    DidChangeWorkspaceFoldersParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams, Hover,
+    HoverParams, InitializeParams, LineIndex, MarkupContent, Message, Notification,
+    PublishDiagnosticsParams, Response, ResponseError, ShowMessageParams, TextEdit, WorkspaceEdit,
+};
+
+mod transport;
+
+use lib::{Applicability, Report, Severity};
+use rnix::TextRange;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use vfs::{FileId, VfsEntry};
+
+/// Profiles of the files in a workspace folder, derived from the
+/// `statix.toml` closest to the folder
+struct Workspace {
+    root: PathBuf,
+    profiles: Profiles,
+}
+
+impl Workspace {
+    fn load(root: PathBuf) -> Result<Self, ConfigErr> {
+        let conf_file = ConfFile::discover(&root)?;
+        Self::from_conf_file(root, conf_file)
+    }
+
+    fn from_conf_file(root: PathBuf, conf_file: ConfFile) -> Result<Self, ConfigErr> {
+        let profiles = Profiles::discover(conf_file, &[], &[])?;
+        Ok(Self { root, profiles })
+    }
+
+    fn lint(&self, path: &Path, text: &str) -> Result<Vec<Report>, ConfigErr> {
+        let profile = self.profiles.get(path)?;
+        let vfs_entry = VfsEntry {
+            file_id: FileId(0),
+            file_path: path,
+            contents: text,
+        };
+        Ok(lint_with(vfs_entry, &profile.lints, &profile.session).reports)
+    }
+}
+
+struct Document {
+    version: i32,
+    text: String,
+    reports: Vec<Report>,
+}
+
+pub struct Server<W> {
+    out: W,
+    initialized: bool,
+    shutdown: bool,
+    workspaces: Vec<Workspace>,
+    /// Used for documents that do not live on disk
+    fallback: Workspace,
+    documents: HashMap<String, Document>,
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(out: W) -> Result<Self, ConfigErr> {
+        let fallback = Workspace::from_conf_file(PathBuf::new(), ConfFile::default())?;
+        Ok(Self {
+            out,
+            initialized: false,
+            shutdown: false,
+            workspaces: Vec::new(),
+            fallback,
+            documents: HashMap::new(),
+        })
+    }
+
+    /// Serve requests until the client sends `exit` or closes the stream
+    pub fn run<R: BufRead>(mut self, mut reader: R) -> Result<(), LspErr> {
+        while let Some(body) = transport::read_message(&mut reader)? {
+            let message = match serde_json::from_slice::<Message>(&body) {
+                Ok(message) => message,
+                Err(e) => {
+                    let error = ResponseError::new(protocol::PARSE_ERROR, e.to_string());
+                    self.send(&Response::err(Value::Null, error))?;
+                    continue;
+                }
+            };
+            match (message.id, message.method) {
+                (_, Some(method)) if method == "exit" => break,
+                (Some(id), Some(method)) => {
+                    let response = match self.request(&method, message.params) {
+                        Ok(result) => Response::ok(id, result),
+                        Err(error) => Response::err(id, error),
+                    };
+                    self.send(&response)?;
+                }
+                (None, Some(method)) => self.notification(&method, message.params)?,
+                // responses to server requests, statix never sends any
+                (Some(_), None) => (),
+                (None, None) => {
+                    let error = ResponseError::new(protocol::INVALID_REQUEST, "missing method");
+                    self.send(&Response::err(Value::Null, error))?;
+                }
+            }
+        }
+        if self.shutdown {
+            Ok(())
+        } else {
+            Err(LspErr::ExitBeforeShutdown)
+        }
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, ResponseError> {
+        if self.shutdown {
+            return Err(ResponseError::new(
+                protocol::INVALID_REQUEST,
+                "server is shutting down",
+            ));
+        }
+        if !self.initialized && method != "initialize" {
+            return Err(ResponseError::new(
+                protocol::SERVER_NOT_INITIALIZED,
+                "server is not initialized",
+            ));
+        }
+        match method {
+            "initialize" => {
+                self.initialize(parse(params)?)
+                    .map_err(|e| ResponseError::new(protocol::INTERNAL_ERROR, e.to_string()))?;
+                Ok(capabilities())
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/codeAction" => to_value(self.code_actions(parse(params)?)),
+            "textDocument/hover" => to_value(self.hover(parse(params)?)),
+            _ => Err(ResponseError::new(
+                protocol::METHOD_NOT_FOUND,
+                format!("unsupported method `{}`", method),
+            )),
+        }
+    }
+
+    // notifications with malformed params cannot be responded to, they are dropped
+    fn notification(&mut self, method: &str, params: Value) -> Result<(), LspErr> {
+        if !self.initialized || self.shutdown {
+            return Ok(());
+        }
+        match method {
+            "textDocument/didOpen" => {
+                if let Ok(params) = parse::<DidOpenTextDocumentParams>(params) {
+                    let document = params.text_document;
+                    self.update(document.uri, document.version, document.text)?;
+                }
+            }
+            "textDocument/didChange" => {
+                if let Ok(mut params) = parse::<DidChangeTextDocumentParams>(params) {
+                    if let Some(change) = params.content_changes.pop() {
+                        let document = params.text_document;
+                        self.update(document.uri, document.version, change.text)?;
+                    }
+                }
+            }
+            "textDocument/didClose" => {
+                if let Ok(params) = parse::<DidCloseTextDocumentParams>(params) {
+                    let uri = params.text_document.uri;
+                    self.documents.remove(&uri);
+                    self.publish(&uri)?;
+                }
+            }
+            "workspace/didChangeWorkspaceFolders" => {
+                if let Ok(params) = parse::<DidChangeWorkspaceFoldersParams>(params) {
+                    let removed = params
+                        .event
+                        .removed
+                        .iter()
+                        .filter_map(|folder| uri_to_path(&folder.uri))
+                        .collect::<Vec<_>>();
+                    self.workspaces.retain(|w| !removed.contains(&w.root));
+                    for folder in params.event.added {
+                        if let Some(root) = uri_to_path(&folder.uri) {
+                            let workspace = self.load_workspace(root)?;
+                            self.workspaces.push(workspace);
+                        }
+                    }
+                    self.relint_all()?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn initialize(&mut self, params: InitializeParams) -> Result<(), LspErr> {
+        // `rootUri` is only consulted by clients that do not support workspace folders
+        let roots = match params.workspace_folders {
+            Some(folders) => folders.into_iter().map(|f| f.uri).collect(),
+            None => params.root_uri.into_iter().collect::<Vec<_>>(),
+        };
+        for root in roots.iter().filter_map(|uri| uri_to_path(uri)) {
+            let workspace = self.load_workspace(root)?;
+            self.workspaces.push(workspace);
+        }
+        self.initialized = true;
+        Ok(())
+    }
+
+    fn load_workspace(&mut self, root: PathBuf) -> Result<Workspace, LspErr> {
+        match Workspace::load(root.clone()) {
+            Ok(workspace) => Ok(workspace),
+            Err(e) => {
+                self.show_error(format!(
+                    "statix: unable to load config for `{}`: {}",
+                    root.display(),
+                    e
+                ))?;
+                Ok(Workspace::from_conf_file(root, ConfFile::default())
+                    .expect("the default config is valid"))
+            }
+        }
+    }
+
+    // picks the innermost workspace folder containing the path, files outside
+    // every workspace folder get a folder of their own
+    fn workspace_for(&mut self, path: Option<&Path>) -> Result<&Workspace, LspErr> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(&self.fallback),
+        };
+        let innermost = self
+            .workspaces
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| path.starts_with(&w.root))
+            .max_by_key(|(_, w)| w.root.components().count())
+            .map(|(idx, _)| idx);
+        let idx = match innermost {
+            Some(idx) => idx,
+            None => {
+                let root = path.parent().unwrap_or(path).to_path_buf();
+                let workspace = self.load_workspace(root)?;
+                self.workspaces.push(workspace);
+                self.workspaces.len() - 1
+            }
+        };
+        Ok(&self.workspaces[idx])
+    }
+
+    fn update(&mut self, uri: String, version: i32, text: String) -> Result<(), LspErr> {
+        let path = uri_to_path(&uri);
+        let lint_path = path.clone().unwrap_or_else(|| PathBuf::from(&uri));
+        let reports = match self.workspace_for(path.as_deref())?.lint(&lint_path, &text) {
+            Ok(reports) => reports,
+            Err(e) => {
+                self.show_error(format!(
+                    "statix: unable to load config for `{}`: {}",
+                    lint_path.display(),
+                    e
+                ))?;
+                self.fallback.lint(&lint_path, &text).unwrap_or_default()
+            }
+        };
+        let document = Document {
+            version,
+            text,
+            reports,
+        };
+        self.documents.insert(uri.clone(), document);
+        self.publish(&uri)
+    }
+
+    fn relint_all(&mut self) -> Result<(), LspErr> {
+        let documents = self
+            .documents
+            .drain()
+            .map(|(uri, d)| (uri, d.version, d.text))
+            .collect::<Vec<_>>();
+        for (uri, version, text) in documents {
+            self.update(uri, version, text)?;
+        }
+        Ok(())
+    }
+
+    fn publish(&mut self, uri: &str) -> Result<(), LspErr> {
+        let params = match self.documents.get(uri) {
+            Some(document) => {
+                let index = &LineIndex::new(&document.text);
+                PublishDiagnosticsParams {
+                    uri: uri.to_owned(),
+                    version: Some(document.version),
+                    diagnostics: document
+                        .reports
+                        .iter()
+                        .flat_map(|report| {
+                            report
+                                .diagnostics
+                                .iter()
+                                .map(move |d| to_diagnostic(report, d, index))
+                        })
+                        .collect(),
+                }
+            }
+            // closed documents have their diagnostics cleared
+            None => PublishDiagnosticsParams {
+                uri: uri.to_owned(),
+                version: None,
+                diagnostics: Vec::new(),
+            },
+        };
+        self.send(&Notification::new(
+            "textDocument/publishDiagnostics",
+            params,
+        ))
+    }
+
+    fn code_actions(&self, params: CodeActionParams) -> Vec<CodeAction> {
+        let uri = params.text_document.uri;
+        let document = match self.documents.get(&uri) {
+            Some(document) => document,
+            None => return Vec::new(),
+        };
+        let index = LineIndex::new(&document.text);
+        let (start, end) = (
+            index.offset(params.range.start),
+            index.offset(params.range.end),
+        );
+        if start > end {
+            return Vec::new();
+        }
+        let requested = TextRange::new(start, end);
+        document
+            .reports
+            .iter()
+            .flat_map(|report| report.diagnostics.iter().map(move |d| (report, d)))
+            .filter(|(_, d)| d.at.intersect(requested).is_some())
+            .filter_map(|(report, d)| {
+                let suggestion = d
+                    .suggestion
+                    .as_ref()
+                    .filter(|s| s.applicability != Applicability::DisplayOnly)?;
+                let edits = suggestion
+                    .edits
+                    .iter()
+                    .map(|e| TextEdit {
+                        range: index.range(e.at),
+                        new_text: e.fix.to_string(),
+                    })
+                    .collect();
+                let title = match suggestion.applicability {
+                    Applicability::Unsafe => {
+                        format!("Fix {}: {} (unsafe)", code(report), report.note)
+                    }
+                    _ => format!("Fix {}: {}", code(report), report.note),
+                };
+                Some(CodeAction {
+                    title,
+                    kind: "quickfix",
+                    diagnostics: vec![to_diagnostic(report, d, &index)],
+                    edit: WorkspaceEdit {
+                        changes: std::iter::once((uri.clone(), edits)).collect(),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        let index = LineIndex::new(&document.text);
+        let offset = index.offset(params.position);
+        let (report, diagnostic) = document
+            .reports
+            .iter()
+            .flat_map(|report| report.diagnostics.iter().map(move |d| (report, d)))
+            .find(|(_, d)| d.at.contains_inclusive(offset))?;
+        let explanation = explain::explain(report.code).ok()?;
+        Some(Hover {
+            contents: MarkupContent {
+                kind: "markdown",
+                value: format!("**{}** {}\n\n{}", code(report), report.note, explanation),
+            },
+            range: index.range(diagnostic.at),
+        })
+    }
+
+    fn show_error(&mut self, message: String) -> Result<(), LspErr> {
+        let params = ShowMessageParams { typ: 1, message };
+        self.send(&Notification::new("window/showMessage", params))
+    }
+
+    fn send<T: Serialize>(&mut self, message: &T) -> Result<(), LspErr> {
+        transport::write_message(&mut self.out, message).map_err(LspErr::Io)
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": {
+                "openClose": true,
+                "change": 1
+            },
+            "codeActionProvider": {
+                "codeActionKinds": ["quickfix"]
+            },
+            "hoverProvider": true,
+            "workspace": {
+                "workspaceFolders": {
+                    "supported": true,
+                    "changeNotifications": true
+                }
+            }
+        },
+        "serverInfo": {
+            "name": "statix",
+            "version": env!("CARGO_PKG_VERSION")
+        }
+    })
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, ResponseError> {
+    serde_json::from_value(params)
+        .map_err(|e| ResponseError::new(protocol::INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value<T: Serialize>(result: T) -> Result<Value, ResponseError> {
+    serde_json::to_value(result)
+        .map_err(|e| ResponseError::new(protocol::INTERNAL_ERROR, e.to_string()))
+}
+
+// same convention as errfmt: W04, E00 and so on
+fn code(report: &Report) -> String {
+    let prefix = match report.severity {
+        Severity::Warn => 'W',
+        Severity::Error => 'E',
+        Severity::Hint => 'I',
+    };
+    format!("{}{:02}", prefix, report.code)
+}
+
+fn to_diagnostic(report: &Report, diagnostic: &lib::Diagnostic, index: &LineIndex) -> Diagnostic {
+    Diagnostic {
+        range: index.range(diagnostic.at),
+        severity: match report.severity {
+            Severity::Error => 1,
+            Severity::Warn => 2,
+            Severity::Hint => 4,
+        },
+        code: code(report),
+        source: "statix",
+        message: diagnostic.message.clone(),
+    }
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = path.bytes();
+    let mut decoded = Vec::with_capacity(path.len());
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            decoded.push(b);
+        }
+    }
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+pub mod main {
+    use std::io;
+
+    use super::Server;
+    use crate::{config::Lsp as LspConfig, err::StatixErr};
+
+    pub fn main(_lsp_config: LspConfig) -> Result<(), StatixErr> {
+        let stdin = io::stdin();
+        let server = Server::new(io::stdout())?;
+        // the exit code tells the client whether shutdown was requested first
+        if let Err(e) = server.run(stdin.lock()) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(messages: &[Value]) -> Vec<u8> {
+        let mut input = Vec::new();
+        for message in messages {
+            transport::write_message(&mut input, message).unwrap();
+        }
+        input
+    }
+
+    fn run(messages: &[Value]) -> (Result<(), LspErr>, Vec<Value>) {
+        let mut out = Vec::new();
+        let result = Server::new(&mut out)
+            .unwrap()
+            .run(frame(messages).as_slice());
+        let mut reader = out.as_slice();
+        let mut sent = Vec::new();
+        while let Some(body) = transport::read_message(&mut reader).unwrap() {
+            sent.push(serde_json::from_slice(&body).unwrap());
+        }
+        (result, sent)
+    }
+
+    #[test]
+    fn round_trip() {
+        let uri = "untitled:default.nix";
+        let (result, sent) = run(&[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": {
+                    "textDocument": { "uri": uri, "version": 1, "text": "{ a = a; }\n" }
+                }
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "textDocument/codeAction",
+                "params": {
+                    "textDocument": { "uri": uri },
+                    "range": {
+                        "start": { "line": 0, "character": 3 },
+                        "end": { "line": 0, "character": 3 }
+                    }
+                }
+            }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ]);
+        assert!(result.is_ok());
+        assert_eq!(sent.len(), 4);
+
+        assert_eq!(sent[0]["id"], 1);
+        assert_eq!(
+            sent[0]["result"]["capabilities"]["hoverProvider"],
+            json!(true)
+        );
+
+        assert_eq!(sent[1]["method"], "textDocument/publishDiagnostics");
+        assert_eq!(sent[1]["params"]["uri"], uri);
+        let diagnostic = &sent[1]["params"]["diagnostics"][0];
+        assert_eq!(diagnostic["code"], "W03");
+        assert_eq!(
+            diagnostic["range"]["start"],
+            json!({ "line": 0, "character": 2 })
+        );
+
+        assert_eq!(sent[2]["id"], 2);
+        let action = &sent[2]["result"][0];
+        assert_eq!(action["kind"], "quickfix");
+        assert_eq!(action["edit"]["changes"][uri][0]["newText"], "inherit a;");
+
+        assert_eq!(
+            sent[3],
+            json!({ "jsonrpc": "2.0", "id": 3, "result": null })
+        );
+    }
+
+    #[test]
+    fn exit_before_shutdown() {
+        let (result, sent) = run(&[json!({ "jsonrpc": "2.0", "method": "exit" })]);
+        assert!(matches!(result, Err(LspErr::ExitBeforeShutdown)));
+        assert!(sent.is_empty());
+    }
+
+    #[test]
+    fn not_initialized() {
+        let (_, sent) = run(&[json!({ "jsonrpc": "2.0", "id": 1, "method": "shutdown" })]);
+        assert_eq!(sent[0]["error"]["code"], protocol::SERVER_NOT_INITIALIZED);
+    }
+}
@@ -0,0 +1,349 @@
+//! The subset of the language server protocol that statix speaks.
+
+use std::collections::HashMap;
+
+use rnix::TextSize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+pub const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+/// An incoming request or notification, notifications have no `id`
+#[derive(Deserialize, Debug)]
+pub struct Message {
+    pub id: Option<Value>,
+    pub method: Option<String>,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+enum Outcome {
+    Result { result: Value },
+    Error { error: ResponseError },
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResponseError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl Response {
+    pub fn ok(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            outcome: Outcome::Result { result },
+        }
+    }
+    pub fn err(id: Value, error: ResponseError) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            outcome: Outcome::Error { error },
+        }
+    }
+}
+
+impl ResponseError {
+    pub fn new<S: AsRef<str>>(code: i64, message: S) -> Self {
+        Self {
+            code,
+            message: message.as_ref().into(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Notification<T> {
+    jsonrpc: &'static str,
+    method: &'static str,
+    params: T,
+}
+
+impl<T> Notification<T> {
+    pub fn new(method: &'static str, params: T) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            method,
+            params,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeParams {
+    pub root_uri: Option<String>,
+    pub workspace_folders: Option<Vec<WorkspaceFolder>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WorkspaceFolder {
+    pub uri: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DidChangeWorkspaceFoldersParams {
+    pub event: WorkspaceFoldersChangeEvent,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WorkspaceFoldersChangeEvent {
+    pub added: Vec<WorkspaceFolder>,
+    pub removed: Vec<WorkspaceFolder>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TextDocumentIdentifier {
+    pub uri: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TextDocumentItem {
+    pub uri: String,
+    pub version: i32,
+    pub text: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VersionedTextDocumentIdentifier {
+    pub uri: String,
+    pub version: i32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DidOpenTextDocumentParams {
+    pub text_document: TextDocumentItem,
+}
+
+/// Only full document sync is advertised, every change carries the
+/// entire text of the document
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DidChangeTextDocumentParams {
+    pub text_document: VersionedTextDocumentIdentifier,
+    pub content_changes: Vec<TextDocumentContentChangeEvent>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TextDocumentContentChangeEvent {
+    pub text: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DidCloseTextDocumentParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeActionParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HoverParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Position,
+}
+
+/// Zero-based line and UTF-16 column
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: u8,
+    pub code: String,
+    pub source: &'static str,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PublishDiagnosticsParams {
+    pub uri: String,
+    pub version: Option<i32>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ShowMessageParams {
+    #[serde(rename = "type")]
+    pub typ: u8,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CodeAction {
+    pub title: String,
+    pub kind: &'static str,
+    pub diagnostics: Vec<Diagnostic>,
+    pub edit: WorkspaceEdit,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WorkspaceEdit {
+    pub changes: HashMap<String, Vec<TextEdit>>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TextEdit {
+    pub range: Range,
+    pub new_text: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Hover {
+    pub contents: MarkupContent,
+    pub range: Range,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MarkupContent {
+    pub kind: &'static str,
+    pub value: String,
+}
+
+/// Converts between byte offsets and LSP positions for a single document
+pub struct LineIndex<'a> {
+    src: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(src: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self { src, line_starts }
+    }
+
+    pub fn position(&self, offset: TextSize) -> Position {
+        let offset = usize::from(offset).min(self.src.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let character = self.src[self.line_starts[line]..offset]
+            .encode_utf16()
+            .count();
+        Position {
+            line: line as u32,
+            character: character as u32,
+        }
+    }
+
+    pub fn range(&self, at: rnix::TextRange) -> Range {
+        Range {
+            start: self.position(at.start()),
+            end: self.position(at.end()),
+        }
+    }
+
+    /// Positions past the end of a line are clamped to the end of that
+    /// line, and positions past the last line to the end of the document
+    pub fn offset(&self, position: Position) -> TextSize {
+        let start = match self.line_starts.get(position.line as usize) {
+            Some(&start) => start,
+            None => return TextSize::of(self.src),
+        };
+        let line = self.src[start..].split('\n').next().unwrap_or_default();
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let mut units = 0;
+        let within = line
+            .char_indices()
+            .find(|(_, c)| {
+                units += c.len_utf16();
+                units > position.character as usize
+            })
+            .map(|(idx, _)| idx)
+            .unwrap_or_else(|| line.len());
+        TextSize::from((start + within) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: u32, character: u32) -> Position {
+        Position { line, character }
+    }
+
+    // offsets between a carriage return and a newline have no
+    // position of their own
+    fn roundtrip(src: &str) {
+        let index = LineIndex::new(src);
+        let offsets = src
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(src.len()))
+            .filter(|&offset| !src[..offset].ends_with('\r'));
+        for offset in offsets {
+            let offset = TextSize::from(offset as u32);
+            assert_eq!(index.offset(index.position(offset)), offset, "in {:?}", src);
+        }
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        // `𝕏` is one char, four bytes and two UTF-16 code units
+        let src = "a𝕏b\n𝕏";
+        let index = LineIndex::new(src);
+        assert_eq!(index.position(TextSize::from(5)), pos(0, 3));
+        assert_eq!(index.offset(pos(0, 3)), TextSize::from(5));
+        assert_eq!(index.position(TextSize::from(11)), pos(1, 2));
+        // a position within a pair points at the start of it
+        assert_eq!(index.offset(pos(0, 2)), TextSize::from(1));
+        roundtrip(src);
+    }
+
+    #[test]
+    fn crlf() {
+        let src = "a = 1;\r\nb = 2;\r\n";
+        let index = LineIndex::new(src);
+        assert_eq!(index.position(TextSize::from(8)), pos(1, 0));
+        assert_eq!(index.offset(pos(1, 2)), TextSize::from(10));
+        // the end of a line is before the carriage return
+        assert_eq!(index.offset(pos(0, 100)), TextSize::from(6));
+        roundtrip(src);
+    }
+
+    #[test]
+    fn clamping() {
+        let src = "ab\ncd";
+        let index = LineIndex::new(src);
+        assert_eq!(index.offset(pos(0, 10)), TextSize::from(2));
+        assert_eq!(index.offset(pos(1, 10)), TextSize::from(5));
+        assert_eq!(index.offset(pos(5, 0)), TextSize::from(5));
+        assert_eq!(index.position(TextSize::from(100)), pos(1, 2));
+    }
+}
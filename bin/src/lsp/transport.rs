@@ -0,0 +1,92 @@
+//! Content-Length framed JSON-RPC messages over a byte stream.
+
+use std::io::{self, BufRead, Write};
+
+use crate::err::LspErr;
+
+use serde::Serialize;
+
+/// Reads the body of the next message, `None` once the stream is closed
+pub fn read_message<R: BufRead>(reader: &mut R) -> Result<Option<Vec<u8>>, LspErr> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        // other headers, such as Content-Type, are ignored
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let length = value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| LspErr::InvalidHeader(header.to_owned()))?;
+                content_length = Some(length);
+            }
+        } else {
+            return Err(LspErr::InvalidHeader(header.to_owned()));
+        }
+    }
+    let length = content_length.ok_or(LspErr::MissingContentLength)?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+pub fn write_message<W: Write, T: Serialize>(writer: &mut W, message: &T) -> io::Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(input: &str) -> Result<Option<Vec<u8>>, LspErr> {
+        read_message(&mut input.as_bytes())
+    }
+
+    #[test]
+    fn framing() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &"hello").unwrap();
+        let mut reader = buffer.as_slice();
+        assert_eq!(read_message(&mut reader).unwrap().unwrap(), b"\"hello\"");
+        assert!(read_message(&mut reader).unwrap().is_none());
+        let body = read("Content-Type: application/vscode-jsonrpc\r\ncontent-length: 2\r\n\r\n{}");
+        assert_eq!(body.unwrap().unwrap(), b"{}");
+    }
+
+    #[test]
+    fn missing_content_length() {
+        assert!(matches!(
+            read("Content-Type: application/vscode-jsonrpc\r\n\r\n{}"),
+            Err(LspErr::MissingContentLength)
+        ));
+    }
+
+    #[test]
+    fn invalid_content_length() {
+        assert!(matches!(
+            read("Content-Length: two\r\n\r\n{}"),
+            Err(LspErr::InvalidHeader(h)) if h == "Content-Length: two"
+        ));
+        assert!(matches!(
+            read("Content-Length 2\r\n\r\n{}"),
+            Err(LspErr::InvalidHeader(_))
+        ));
+    }
+
+    #[test]
+    fn truncated_body() {
+        assert!(matches!(
+            read("Content-Length: 10\r\n\r\n{}"),
+            Err(LspErr::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof
+        ));
+    }
+}
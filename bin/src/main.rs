@@ -4,7 +4,7 @@ use clap::Parser;
 use statix::{
     config::{Opts, SubCommand},
     err::StatixErr,
-    lint, fix, explain, dump, list,
+    config, lint, fix, explain, dump, list, cache,
 };
 
 fn _main() -> Result<(), StatixErr> {
@@ -16,6 +16,10 @@ fn _main() -> Result<(), StatixErr> {
         SubCommand::Explain(config) => explain::main::main(config),
         SubCommand::Dump(_) => dump::main::main(),
         SubCommand::List(_) => list::main::main(),
+        SubCommand::Cache(config) => cache::main::main(config),
+        SubCommand::Config(config) => config::main::main(config),
+        #[cfg(feature = "lsp")]
+        SubCommand::Lsp(config) => statix::lsp::main::main(config),
     }
 }
 
@@ -0,0 +1,423 @@
+//! Lints and session for each file.
+//!
+//! A file is checked with the settings of the `statix.toml` nearest to
+//! it, on top of those of the config files it inherits from and of the
+//! user config, and of the overrides within them that match the file.
+//! The user config is left out when one of those config files is the
+//! `root`. Files that end up with the same settings share a profile,
+//! which is built the first time one of those files comes up.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use crate::{
+    cache::Cache,
+    config::{ConfFile, Override},
+    err::ConfigErr,
+    LintMap,
+};
+
+use globset::GlobSet;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use lib::session::SessionInfo;
+
+/// What a file is checked with
+pub struct Profile {
+    pub lints: LintMap,
+    pub session: SessionInfo,
+    // made the first time a file of the profile is checked
+    cache: OnceLock<Option<Cache>>,
+}
+
+impl Profile {
+    /// The cache of results produced with this profile, `None` if
+    /// there is nowhere to keep it
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache
+            .get_or_init(|| Cache::new(&self.lints, &self.session))
+            .as_ref()
+    }
+}
+
+struct Layer {
+    conf_file: ConfFile,
+    matchers: Vec<GlobSet>,
+    // the `ignore` list, rooted at the directory of the config file
+    ignore: Gitignore,
+}
+
+/// A config file along with those it inherits from, outermost first
+type Chain = Arc<Vec<Layer>>;
+
+/// Config files that apply to a file, along with the indices of their
+/// overrides that match it
+type Key = Vec<(PathBuf, Vec<usize>)>;
+
+pub struct Profiles {
+    select: Vec<String>,
+    ignore: Vec<String>,
+    /// Used for files without a config file of their own, and for
+    /// every file unless config files are discovered
+    fallback: Chain,
+    discover: bool,
+    user: Option<ConfFile>,
+    // by the path of the innermost config file
+    chains: RwLock<HashMap<PathBuf, Chain>>,
+    // the nearest config file of each directory seen so far
+    nearest: RwLock<HashMap<PathBuf, Option<PathBuf>>>,
+    built: RwLock<HashMap<Key, Arc<Profile>>>,
+}
+
+impl Profiles {
+    /// Profiles built from `conf_file` alone, lints are narrowed down
+    /// by `select` and `ignore` as in `ConfFile::lints_with`
+    pub fn new(
+        conf_file: ConfFile,
+        select: &[String],
+        ignore: &[String],
+    ) -> Result<Self, ConfigErr> {
+        Self::with(conf_file, ConfFile::user()?, select, ignore, false)
+    }
+
+    /// Profiles built from the config file nearest to each file, files
+    /// without one fall back to `conf_file`
+    pub fn discover(
+        conf_file: ConfFile,
+        select: &[String],
+        ignore: &[String],
+    ) -> Result<Self, ConfigErr> {
+        Self::with(conf_file, ConfFile::user()?, select, ignore, true)
+    }
+
+    pub(crate) fn with(
+        conf_file: ConfFile,
+        user: Option<ConfFile>,
+        select: &[String],
+        ignore: &[String],
+        discover: bool,
+    ) -> Result<Self, ConfigErr> {
+        let profiles = Self {
+            select: select.to_vec(),
+            ignore: ignore.to_vec(),
+            fallback: chain(conf_file, user.as_ref())?,
+            discover,
+            user,
+            chains: RwLock::new(HashMap::new()),
+            nearest: RwLock::new(HashMap::new()),
+            built: RwLock::new(HashMap::new()),
+        };
+        // mistakes outside of overrides show up before any file is read
+        let key = profiles
+            .fallback
+            .iter()
+            .map(|layer| (layer.conf_file.path().to_owned(), Vec::new()))
+            .collect();
+        profiles.build(&profiles.fallback, key)?;
+        Ok(profiles)
+    }
+
+    /// The profile of the file at `path`
+    pub fn get(&self, path: &Path) -> Result<Arc<Profile>, ConfigErr> {
+        let (chain, key) = self.key(path)?;
+        if let Some(profile) = self.built.read().unwrap().get(&key) {
+            return Ok(Arc::clone(profile));
+        }
+        self.build(&chain, key)
+    }
+
+    /// Config files that apply to the file or directory at `path`,
+    /// outermost first, along with the indices of their overrides that
+    /// match it
+    pub fn layers(&self, path: &Path) -> Result<Vec<(ConfFile, Vec<usize>)>, ConfigErr> {
+        let (chain, key) = self.key(path)?;
+        Ok(chain
+            .iter()
+            .zip(key)
+            .map(|(layer, (_, matched))| (layer.conf_file.clone(), matched))
+            .collect())
+    }
+
+    /// Whether the file or directory at `path` is matched by the
+    /// `ignore` list of a config file that applies to it. Patterns are
+    /// relative to the directory of their config file.
+    pub fn ignores(&self, path: &Path, is_dir: bool) -> bool {
+        let absolute = match fs::canonicalize(path) {
+            Ok(absolute) => absolute,
+            Err(_) => return false,
+        };
+        let dir = if is_dir {
+            Some(absolute.as_path())
+        } else {
+            absolute.parent()
+        };
+        // a config file that does not load is reported once the files
+        // it applies to are checked
+        let chain = match dir {
+            Some(dir) if self.discover => self
+                .chain_of(dir)
+                .unwrap_or_else(|_| Arc::clone(&self.fallback)),
+            _ => Arc::clone(&self.fallback),
+        };
+        chain
+            .iter()
+            .any(|layer| layer.ignore.matched(&absolute, is_dir).is_ignore())
+    }
+
+    /// Paths of the config files read so far
+    pub fn conf_files(&self) -> Vec<PathBuf> {
+        let chains = self.chains.read().unwrap();
+        let mut paths = self
+            .fallback
+            .iter()
+            .chain(chains.values().flat_map(|chain| chain.iter()))
+            .map(|layer| layer.conf_file.path().to_owned())
+            // the default config is not read from anywhere
+            .filter(|path| !path.as_os_str().is_empty())
+            .collect::<Vec<_>>();
+        paths.sort_unstable();
+        paths.dedup();
+        paths
+    }
+
+    fn key(&self, path: &Path) -> Result<(Chain, Key), ConfigErr> {
+        let absolute = fs::canonicalize(path).ok();
+        let dir = match absolute.as_deref() {
+            Some(p) if p.is_dir() => Some(p),
+            p => p.and_then(Path::parent),
+        };
+        let chain = match dir {
+            Some(dir) if self.discover => self.chain_of(dir)?,
+            _ => Arc::clone(&self.fallback),
+        };
+        let key = chain
+            .iter()
+            .map(|layer| {
+                let path = relative(path, absolute.as_deref(), layer.conf_file.dir());
+                let matched = layer
+                    .matchers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, m)| m.is_match(&path))
+                    .map(|(i, _)| i)
+                    .collect();
+                (layer.conf_file.path().to_owned(), matched)
+            })
+            .collect::<Key>();
+        Ok((chain, key))
+    }
+
+    fn build(&self, chain: &[Layer], key: Key) -> Result<Arc<Profile>, ConfigErr> {
+        let conf_file = chain
+            .iter()
+            .zip(key.iter())
+            .map(|(layer, (_, matched))| layer.conf_file.overridden(matched))
+            .reduce(|base, conf_file| conf_file.inherit(&base))
+            .unwrap_or_default();
+        let profile = Profile {
+            lints: conf_file.lints_with(&self.select, &self.ignore)?,
+            session: SessionInfo::from_version(conf_file.version()?)
+                .with_options(conf_file.options()),
+            cache: OnceLock::new(),
+        };
+        let mut built = self.built.write().unwrap();
+        Ok(Arc::clone(
+            built.entry(key).or_insert_with(|| Arc::new(profile)),
+        ))
+    }
+
+    // the chain of the config file nearest to `dir`
+    fn chain_of(&self, dir: &Path) -> Result<Chain, ConfigErr> {
+        let cached = self.nearest.read().unwrap().get(dir).cloned();
+        let nearest = cached.unwrap_or_else(|| {
+            let nearest = ConfFile::nearest(dir);
+            self.nearest
+                .write()
+                .unwrap()
+                .insert(dir.to_owned(), nearest.clone());
+            nearest
+        });
+        let path = match nearest {
+            Some(path) => path,
+            None => return Ok(Arc::clone(&self.fallback)),
+        };
+        if let Some(chain) = self.chains.read().unwrap().get(&path) {
+            return Ok(Arc::clone(chain));
+        }
+        let chain = chain(ConfFile::from_path(&path)?, self.user.as_ref())?;
+        let mut chains = self.chains.write().unwrap();
+        Ok(Arc::clone(chains.entry(path).or_insert(chain)))
+    }
+}
+
+// the user config comes first, unless a config file of the chain is
+// the `root`
+fn chain(conf_file: ConfFile, user: Option<&ConfFile>) -> Result<Chain, ConfigErr> {
+    let chain = conf_file.chain()?;
+    let user = user.filter(|_| !chain.iter().any(ConfFile::is_root));
+    let layers = user
+        .cloned()
+        .into_iter()
+        .chain(chain)
+        .map(|conf_file| {
+            let matchers = conf_file
+                .overrides()
+                .iter()
+                .map(Override::matcher)
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut ignore = GitignoreBuilder::new(conf_file.dir());
+            for line in conf_file.ignore.iter() {
+                ignore.add_line(None, line)?;
+            }
+            Ok(Layer {
+                ignore: ignore.build()?,
+                conf_file,
+                matchers,
+            })
+        })
+        .collect::<Result<Vec<_>, ConfigErr>>()?;
+    Ok(Arc::new(layers))
+}
+
+// paths are matched relative to the directory of the config file,
+// paths outside of it are matched as they were given
+fn relative(path: &Path, absolute: Option<&Path>, dir: &Path) -> PathBuf {
+    absolute
+        .and_then(|p| p.strip_prefix(dir).ok())
+        .unwrap_or(path)
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tree;
+
+    fn names(profile: &Profile) -> Vec<&'static str> {
+        let mut names = profile
+            .lints
+            .values()
+            .flatten()
+            .map(|l| l.name())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    #[test]
+    fn overrides() {
+        let conf_file: ConfFile = toml::de::from_str(
+            r#"
+            nix_version = "2.4"
+            disabled = ["empty_pattern"]
+
+            [[overrides]]
+            files = ["vendor/**"]
+            enabled = ["perf"]
+            nix_version = "2.3"
+
+            [[overrides]]
+            files = ["*.generated.nix"]
+            disabled = ["faster_*"]
+            "#,
+        )
+        .unwrap();
+        let profiles = Profiles::with(conf_file, None, &[], &[], false).unwrap();
+
+        let plain = profiles.get(Path::new("./default.nix")).unwrap();
+        assert!(!names(&plain).contains(&"empty_pattern"));
+        assert!(names(&plain).contains(&"eta_reduction"));
+
+        let vendored = profiles.get(Path::new("vendor/overlay/a.nix")).unwrap();
+        assert_eq!(
+            names(&vendored),
+            vec!["faster_groupby", "faster_zipattrswith"]
+        );
+        assert_eq!(vendored.session.version(), &"2.3".parse().unwrap());
+
+        let both = profiles
+            .get(Path::new("vendor/pkgs/b.generated.nix"))
+            .unwrap();
+        assert!(names(&both).is_empty());
+
+        // globs with a slash match from the directory of the config file
+        let nested = profiles.get(Path::new("a/vendor/b.nix")).unwrap();
+        assert!(Arc::ptr_eq(&nested, &plain));
+    }
+
+    #[test]
+    fn ignore_of_every_config_file() {
+        let root = tree(
+            "ignore",
+            &[
+                ("user/statix.toml", "ignore = [\"result\"]"),
+                ("project/statix.toml", "ignore = [\"gen\"]"),
+                ("project/shared.toml", "ignore = [\"*.generated.nix\"]"),
+                (
+                    "project/sub/statix.toml",
+                    "extends = \"../shared.toml\"\nignore = [\"/vendor\"]",
+                ),
+                ("project/gen/a.nix", ""),
+                ("project/vendor/a.nix", ""),
+                ("project/result/a.nix", ""),
+                ("project/b.generated.nix", ""),
+                ("project/sub/vendor/a.nix", ""),
+                ("project/sub/a.generated.nix", ""),
+                ("project/sub/b.nix", ""),
+            ],
+        );
+        let project = root.join("project");
+        let conf_file = ConfFile::from_path(project.join("statix.toml")).unwrap();
+        let user = ConfFile::from_path(root.join("user/statix.toml")).unwrap();
+        let profiles = Profiles::with(conf_file, Some(user), &[], &[], true).unwrap();
+        let ignores = |path: &str, is_dir| profiles.ignores(&project.join(path), is_dir);
+
+        assert!(ignores("gen", true));
+        assert!(ignores("result", true));
+        assert!(ignores("sub/vendor", true));
+        assert!(ignores("sub/a.generated.nix", false));
+        // patterns are relative to the directory of their config file
+        assert!(!ignores("vendor", true));
+        // and only apply to the files that config file applies to
+        assert!(!ignores("b.generated.nix", false));
+        assert!(!ignores("sub/b.nix", false));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn root_leaves_out_user_config() {
+        let root = tree(
+            "root",
+            &[
+                ("user/statix.toml", "disabled = [\"eta_reduction\"]"),
+                ("project/statix.toml", "disabled = [\"empty_pattern\"]"),
+                ("project/sub/statix.toml", "root = true"),
+                (
+                    "project/other/statix.toml",
+                    "extends = \"../sub/statix.toml\"",
+                ),
+            ],
+        );
+        let project = root.join("project");
+        let conf_file = ConfFile::from_path(project.join("statix.toml")).unwrap();
+        let user = ConfFile::from_path(root.join("user/statix.toml")).unwrap();
+        let profiles = Profiles::with(conf_file, Some(user), &[], &[], true).unwrap();
+        let names = |dir: &str| names(&profiles.get(&project.join(dir)).unwrap());
+
+        assert!(!names(".").contains(&"eta_reduction"));
+        assert!(!names(".").contains(&"empty_pattern"));
+        assert!(names("sub").contains(&"eta_reduction"));
+        assert!(names("sub").contains(&"empty_pattern"));
+        // a config file that extends the root is cut off too
+        assert!(names("other").contains(&"eta_reduction"));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -3,7 +3,11 @@ use std::{
     str,
 };
 
-use crate::{config::OutFormat, lint::LintResult};
+use crate::{
+    config::OutFormat,
+    lint::LintResult,
+    utils::{code, lint_name},
+};
 
 use ariadne::{
     CharSet, Color, Config as CliConfig, Fmt, Label, LabelAttach, Report as CliReport,
@@ -20,6 +24,21 @@ pub trait WriteDiagnostic {
         vfs: &ReadOnlyVfs,
         format: OutFormat,
     ) -> io::Result<()>;
+
+    /// Write results of all files at once, formats such as SARIF
+    /// describe an entire run in a single document
+    fn write_results(
+        &mut self,
+        results: &[LintResult],
+        vfs: &ReadOnlyVfs,
+        format: OutFormat,
+    ) -> io::Result<()>;
+
+    /// Clear previously written results before writing them anew, as
+    /// watch mode does. Only the human readable format clears the
+    /// screen, the others are meant to be read by other programs and
+    /// are separated by an empty line instead.
+    fn clear(&mut self, format: OutFormat) -> io::Result<()>;
 }
 
 impl<T> WriteDiagnostic for T
@@ -35,9 +54,40 @@ where
         match format {
             #[cfg(feature = "json")]
             OutFormat::Json => json::write_json(self, lint_result, vfs),
+            #[cfg(feature = "json")]
+            OutFormat::Sarif => sarif::write_sarif(self, std::slice::from_ref(lint_result), vfs),
             OutFormat::StdErr => write_stderr(self, lint_result, vfs),
             OutFormat::Errfmt => write_errfmt(self, lint_result, vfs),
+            OutFormat::Checkstyle => write_checkstyle(self, std::slice::from_ref(lint_result), vfs),
+            OutFormat::Junit => write_junit(self, std::slice::from_ref(lint_result), vfs),
+            OutFormat::Github => write_github(self, lint_result, vfs),
+        }
+    }
+
+    fn write_results(
+        &mut self,
+        results: &[LintResult],
+        vfs: &ReadOnlyVfs,
+        format: OutFormat,
+    ) -> io::Result<()> {
+        match format {
+            #[cfg(feature = "json")]
+            OutFormat::Sarif => sarif::write_sarif(self, results, vfs),
+            OutFormat::Checkstyle => write_checkstyle(self, results, vfs),
+            OutFormat::Junit => write_junit(self, results, vfs),
+            _ => results
+                .iter()
+                .try_for_each(|lint_result| WriteDiagnostic::write(self, lint_result, vfs, format)),
+        }
+    }
+
+    fn clear(&mut self, format: OutFormat) -> io::Result<()> {
+        match format {
+            // erase the screen and move the cursor to the top left
+            OutFormat::StdErr => write!(self, "\x1b[2J\x1b[H")?,
+            _ => writeln!(self)?,
         }
+        self.flush()
     }
 }
 
@@ -122,13 +172,159 @@ fn write_errfmt<T: Write>(
     Ok(())
 }
 
+// see https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions
+fn write_github<T: Write>(
+    writer: &mut T,
+    lint_result: &LintResult,
+    vfs: &ReadOnlyVfs,
+) -> io::Result<()> {
+    let file_id = lint_result.file_id;
+    let src = vfs.get_str(file_id);
+    let path = vfs.file_path(file_id);
+    for report in lint_result.reports.iter() {
+        for diagnostic in report.diagnostics.iter() {
+            let (start, end) = (diagnostic.at.start(), diagnostic.at.end());
+            writeln!(
+                writer,
+                "::{command} file={file},line={line},col={col},endLine={end_line},endColumn={end_col},title={title}::{message}",
+                command = match report.severity {
+                    Severity::Warn => "warning",
+                    Severity::Error => "error",
+                    Severity::Hint => "notice",
+                },
+                file = github_escape_property(path.to_str().unwrap_or("<unknown>")),
+                line = line(start, src),
+                col = column(start, src),
+                end_line = line(end, src),
+                end_col = column(end, src),
+                title = github_escape_property(&format!(
+                    "{} {}",
+                    code(report.code),
+                    lint_name(report.code)
+                )),
+                message = github_escape_data(&diagnostic.message),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_checkstyle<T: Write>(
+    writer: &mut T,
+    lint_results: &[LintResult],
+    vfs: &ReadOnlyVfs,
+) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<checkstyle version="4.3">"#)?;
+    for lint_result in lint_results {
+        let file_id = lint_result.file_id;
+        let src = vfs.get_str(file_id);
+        let path = vfs.file_path(file_id);
+        writeln!(
+            writer,
+            r#"  <file name="{}">"#,
+            xml_escape(path.to_str().unwrap_or("<unknown>"))
+        )?;
+        for report in lint_result.reports.iter() {
+            for diagnostic in report.diagnostics.iter() {
+                writeln!(
+                    writer,
+                    r#"    <error line="{}" column="{}" severity="{}" message="{}" source="statix.{}.{}"/>"#,
+                    line(diagnostic.at.start(), src),
+                    column(diagnostic.at.start(), src),
+                    match report.severity {
+                        Severity::Warn => "warning",
+                        Severity::Error => "error",
+                        Severity::Hint => "info",
+                    },
+                    xml_escape(&diagnostic.message),
+                    code(report.code),
+                    lint_name(report.code),
+                )?;
+            }
+        }
+        writeln!(writer, "  </file>")?;
+    }
+    writeln!(writer, "</checkstyle>")?;
+    Ok(())
+}
+
+// every file is a test suite, every diagnostic is a failed test case
+fn write_junit<T: Write>(
+    writer: &mut T,
+    lint_results: &[LintResult],
+    vfs: &ReadOnlyVfs,
+) -> io::Result<()> {
+    let count = |lint_result: &LintResult| {
+        lint_result
+            .reports
+            .iter()
+            .map(|r| r.diagnostics.len())
+            .sum::<usize>()
+    };
+    let total = lint_results.iter().map(count).sum::<usize>();
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<testsuites name="statix" tests="{0}" failures="{0}">"#,
+        total
+    )?;
+    for lint_result in lint_results {
+        let file_id = lint_result.file_id;
+        let src = vfs.get_str(file_id);
+        let path = xml_escape(vfs.file_path(file_id).to_str().unwrap_or("<unknown>"));
+        writeln!(
+            writer,
+            r#"  <testsuite name="{0}" tests="{1}" failures="{1}">"#,
+            path,
+            count(lint_result)
+        )?;
+        for report in lint_result.reports.iter() {
+            for diagnostic in report.diagnostics.iter() {
+                let line = line(diagnostic.at.start(), src);
+                let column = column(diagnostic.at.start(), src);
+                let code = code(report.code);
+                let name = lint_name(report.code);
+                writeln!(
+                    writer,
+                    r#"    <testcase name="{code} {name} at {line}:{column}" classname="{path}">"#,
+                    code = code,
+                    name = name,
+                    line = line,
+                    column = column,
+                    path = path,
+                )?;
+                writeln!(
+                    writer,
+                    r#"      <failure type="{severity}" message="{message}">{path}:{line}:{column}: {code} {note}: {message}</failure>"#,
+                    severity = match report.severity {
+                        Severity::Warn => "warning",
+                        Severity::Error => "error",
+                        Severity::Hint => "hint",
+                    },
+                    message = xml_escape(&diagnostic.message),
+                    path = path,
+                    line = line,
+                    column = column,
+                    code = code,
+                    note = xml_escape(report.note),
+                )?;
+                writeln!(writer, "    </testcase>")?;
+            }
+        }
+        writeln!(writer, "  </testsuite>")?;
+    }
+    writeln!(writer, "</testsuites>")?;
+    Ok(())
+}
+
 #[cfg(feature = "json")]
 mod json {
     use crate::lint::LintResult;
 
     use std::io::{self, Write};
 
-    use lib::Severity;
+    use lib::{Applicability, Severity};
     use rnix::TextRange;
     use serde::Serialize;
     use vfs::ReadOnlyVfs;
@@ -157,6 +353,13 @@ mod json {
 
     #[derive(Serialize)]
     struct JsonSuggestion {
+        at: JsonSpan,
+        edits: Vec<JsonEdit>,
+        applicability: Applicability,
+    }
+
+    #[derive(Serialize)]
+    struct JsonEdit {
         at: JsonSpan,
         fix: String,
     }
@@ -212,7 +415,15 @@ mod json {
                         message: &d.message,
                         suggestion: d.suggestion.as_ref().map(|s| JsonSuggestion {
                             at: JsonSpan::from_textrange(s.at, src),
-                            fix: s.fix.to_string(),
+                            edits: s
+                                .edits
+                                .iter()
+                                .map(|e| JsonEdit {
+                                    at: JsonSpan::from_textrange(e.at, src),
+                                    fix: e.fix.to_string(),
+                                })
+                                .collect(),
+                            applicability: s.applicability,
                         }),
                     })
                     .collect::<Vec<_>>();
@@ -233,6 +444,260 @@ mod json {
     }
 }
 
+#[cfg(feature = "json")]
+mod sarif {
+    use crate::lint::LintResult;
+
+    use std::io::{self, Write};
+
+    use lib::{Applicability, Severity, LINTS};
+    use rnix::TextRange;
+    use serde::Serialize;
+    use vfs::ReadOnlyVfs;
+
+    #[derive(Serialize)]
+    struct Log {
+        #[serde(rename = "$schema")]
+        schema: &'static str,
+        version: &'static str,
+        runs: Vec<Run>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Run {
+        tool: Tool,
+        column_kind: &'static str,
+        results: Vec<SarifResult>,
+    }
+
+    #[derive(Serialize)]
+    struct Tool {
+        driver: Driver,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Driver {
+        name: &'static str,
+        version: &'static str,
+        information_uri: &'static str,
+        rules: Vec<Rule>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Rule {
+        id: String,
+        name: &'static str,
+        short_description: Message,
+        full_description: Message,
+        help: Help,
+    }
+
+    #[derive(Serialize)]
+    struct Help {
+        text: &'static str,
+        markdown: &'static str,
+    }
+
+    #[derive(Serialize)]
+    struct Message {
+        text: String,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct SarifResult {
+        rule_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        rule_index: Option<usize>,
+        level: &'static str,
+        message: Message,
+        locations: Vec<Location>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        fixes: Vec<Fix>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Location {
+        physical_location: PhysicalLocation,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PhysicalLocation {
+        artifact_location: ArtifactLocation,
+        region: Region,
+    }
+
+    #[derive(Serialize, Clone)]
+    struct ArtifactLocation {
+        uri: String,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Region {
+        start_line: usize,
+        start_column: usize,
+        end_line: usize,
+        end_column: usize,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Fix {
+        description: Message,
+        artifact_changes: Vec<ArtifactChange>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ArtifactChange {
+        artifact_location: ArtifactLocation,
+        replacements: Vec<Replacement>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Replacement {
+        deleted_region: Region,
+        inserted_content: Message,
+    }
+
+    impl Region {
+        // columns are counted in characters, see `columnKind` on the run
+        fn from_textrange(at: TextRange, src: &str) -> Self {
+            let column = |at: usize| {
+                let line_start = src[..at].rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+                src[line_start..at].chars().count() + 1
+            };
+            Self {
+                start_line: super::line(at.start(), src),
+                start_column: column(at.start().into()),
+                end_line: super::line(at.end(), src),
+                end_column: column(at.end().into()),
+            }
+        }
+    }
+
+    // rules are sorted by code, a rule's index is used to refer to it from results
+    fn rules() -> Vec<Rule> {
+        let mut lints = (*LINTS).clone();
+        lints.as_mut_slice().sort_by_key(|l| l.code());
+        lints
+            .into_iter()
+            .map(|l| Rule {
+                id: super::code(l.code()),
+                name: l.name(),
+                short_description: Message {
+                    text: l.note().to_owned(),
+                },
+                full_description: Message {
+                    text: l.explanation().to_owned(),
+                },
+                help: Help {
+                    text: l.explanation(),
+                    markdown: l.explanation(),
+                },
+            })
+            .collect()
+    }
+
+    pub fn write_sarif<T: Write>(
+        writer: &mut T,
+        lint_results: &[LintResult],
+        vfs: &ReadOnlyVfs,
+    ) -> io::Result<()> {
+        let rules = rules();
+        let results = lint_results
+            .iter()
+            .flat_map(|lint_result| {
+                let file_id = lint_result.file_id;
+                let path = vfs.file_path(file_id);
+                let src = vfs.get_str(file_id);
+                let artifact_location = ArtifactLocation {
+                    uri: path
+                        .to_string_lossy()
+                        .trim_start_matches("./")
+                        .replace('\\', "/"),
+                };
+                let rules = &rules;
+                lint_result.reports.iter().flat_map(move |r| {
+                    let rule_id = super::code(r.code);
+                    // syntax errors are not lints, they have no rule to refer to
+                    let rule_index = rules.iter().position(|rule| rule.id == rule_id);
+                    let level = match r.severity {
+                        Severity::Error => "error",
+                        Severity::Warn => "warning",
+                        Severity::Hint => "note",
+                    };
+                    let artifact_location = artifact_location.clone();
+                    r.diagnostics.iter().map(move |d| SarifResult {
+                        rule_id: rule_id.clone(),
+                        rule_index,
+                        level,
+                        message: Message {
+                            text: d.message.clone(),
+                        },
+                        locations: vec![Location {
+                            physical_location: PhysicalLocation {
+                                artifact_location: artifact_location.clone(),
+                                region: Region::from_textrange(d.at, src),
+                            },
+                        }],
+                        // fixes are meant to be applied by consumers without
+                        // review, as `statix fix` would by default
+                        fixes: d
+                            .suggestion
+                            .iter()
+                            .filter(|s| s.applicability == Applicability::Safe)
+                            .map(|s| Fix {
+                                description: Message {
+                                    text: r.note.to_owned(),
+                                },
+                                artifact_changes: vec![ArtifactChange {
+                                    artifact_location: artifact_location.clone(),
+                                    replacements: s
+                                        .edits
+                                        .iter()
+                                        .map(|e| Replacement {
+                                            deleted_region: Region::from_textrange(e.at, src),
+                                            inserted_content: Message {
+                                                text: e.fix.to_string(),
+                                            },
+                                        })
+                                        .collect(),
+                                }],
+                            })
+                            .collect(),
+                    })
+                })
+            })
+            .collect();
+        let log = Log {
+            schema: "https://json.schemastore.org/sarif-2.1.0.json",
+            version: "2.1.0",
+            runs: vec![Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: "statix",
+                        version: env!("CARGO_PKG_VERSION"),
+                        information_uri: "https://git.peppe.rs/languages/statix/about",
+                        rules,
+                    },
+                },
+                column_kind: "unicodeCodePoints",
+                results,
+            }],
+        };
+        writeln!(writer, "{}", serde_json::to_string_pretty(&log).unwrap())?;
+        Ok(())
+    }
+}
+
 fn line(at: TextSize, src: &str) -> usize {
     let at = at.into();
     src[..at].chars().filter(|&c| c == '\n').count() + 1
@@ -243,6 +708,31 @@ fn column(at: TextSize, src: &str) -> usize {
     src[..at].rfind('\n').map(|c| at - c).unwrap_or(at + 1)
 }
 
+fn github_escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn github_escape_property(text: &str) -> String {
+    github_escape_data(text)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+fn xml_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_owned(),
+            '<' => "&lt;".to_owned(),
+            '>' => "&gt;".to_owned(),
+            '"' => "&quot;".to_owned(),
+            '\'' => "&apos;".to_owned(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
 // everything within backticks is colorized, backticks are removed
 fn colorize(message: &str) -> String {
     message
@@ -1,12 +1,11 @@
 use std::collections::HashMap;
 
+use crate::LintMap;
+
 use lib::{Lint, LINTS};
-use rnix::SyntaxKind;
 
 #[allow(clippy::borrowed_box)]
-pub fn lint_map_of(
-    lints: &[&'static Box<dyn Lint>],
-) -> HashMap<SyntaxKind, Vec<&'static Box<dyn Lint>>> {
+pub fn lint_map_of(lints: &[&'static Box<dyn Lint>]) -> LintMap {
     let mut map = HashMap::new();
     for lint in lints.iter() {
         let lint = *lint;
@@ -17,11 +16,13 @@ pub fn lint_map_of(
                 .or_insert_with(|| vec![lint]);
         }
     }
-    map
+    LintMap {
+        map,
+        ..Default::default()
+    }
 }
 
-#[allow(clippy::borrowed_box)]
-pub fn lint_map() -> HashMap<SyntaxKind, Vec<&'static Box<dyn Lint>>> {
+pub fn lint_map() -> LintMap {
     lint_map_of(&LINTS)
 }
 
@@ -38,3 +39,54 @@ pub fn get_version_info() -> Option<String> {
 pub fn default_nix_version() -> String {
     String::from("2.4")
 }
+
+/// 64-bit FNV-1a, stable across platforms and releases, unlike the
+/// hasher in std
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// syntax errors are not lints, but they get a code anyway
+pub fn code(code: u32) -> String {
+    if code == 0 {
+        String::from("E00")
+    } else {
+        format!("W{:02}", code)
+    }
+}
+
+pub fn lint_name(code: u32) -> &'static str {
+    LINTS
+        .iter()
+        .find(|l| l.code() == code)
+        .map(|l| l.name())
+        .unwrap_or("syntax_error")
+}
+
+/// A tree of files under the temporary directory, for tests. It is
+/// removed first if it is left over from an earlier run.
+#[cfg(test)]
+pub fn tree(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    use std::fs;
+    let root = std::env::temp_dir().join(format!("statix-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for (path, contents) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    fs::canonicalize(root).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hash;
+
+    #[test]
+    fn stable_hash() {
+        assert_eq!(hash(b""), 0xcbf29ce484222325);
+        assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+    }
+}
@@ -0,0 +1,478 @@
+//! Watch a tree of files for changes, backed by inotify.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::dirs::{self, Ignore};
+
+/// Changes to files of these names call for a full reload, as do
+/// changes to the config files read so far
+const RELOAD_ON: [&str; 2] = ["statix.toml", ".gitignore"];
+
+/// A change to an entry of a watched directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Created, written to, or moved into a watched directory
+    Modified(PathBuf),
+    /// Deleted, or moved out of a watched directory
+    Removed(PathBuf),
+    /// The kernel dropped events, everything has to be reloaded
+    Overflow,
+}
+
+/// Watches directories, changes are reported with paths relative
+/// to the path the directory was watched as
+pub struct Watcher {
+    inner: sys::Inotify,
+    dirs: HashMap<i32, PathBuf>,
+}
+
+impl Watcher {
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            inner: sys::Inotify::new()?,
+            dirs: HashMap::new(),
+        })
+    }
+
+    /// Watch the target of a check or fix: every directory within
+    /// it that is not ignored, and the directory of the config file
+    pub fn watch_target(
+        &mut self,
+        target: &Path,
+        conf_path: &Path,
+        ignore: &Ignore,
+    ) -> io::Result<()> {
+        if target.is_dir() {
+            self.watch_tree(target, ignore)?;
+        } else {
+            let parent = target.parent().unwrap_or_else(|| Path::new(""));
+            self.watch_dir(parent)?;
+        }
+        let conf_dir = if conf_path.is_dir() {
+            conf_path
+        } else {
+            conf_path.parent().unwrap_or_else(|| Path::new(""))
+        };
+        self.watch_dir(conf_dir)
+    }
+
+    /// Watch the directories of config files, such as the user config
+    /// and those named by `extends`, wherever they are
+    pub fn watch_conf_files(&mut self, conf_files: &[PathBuf]) -> io::Result<()> {
+        for dir in conf_files.iter().filter_map(|path| path.parent()) {
+            self.watch_dir(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Watch a directory and all subdirectories that are not ignored
+    pub fn watch_tree(&mut self, dir: &Path, ignore: &Ignore) -> io::Result<()> {
+        if ignore.matched(dir, true) {
+            return Ok(());
+        }
+        self.watch_dir(dir)?;
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            // symlinks are not followed, they could form cycles
+            if entry.file_type()?.is_dir() {
+                self.watch_tree(&entry.path(), ignore)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Watch a single directory, the empty path stands for the
+    /// current directory
+    pub fn watch_dir(&mut self, dir: &Path) -> io::Result<()> {
+        let on_disk = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let wd = self.inner.add_watch(on_disk)?;
+        // the same directory may be watched under different names
+        self.dirs.entry(wd).or_insert_with(|| dir.to_path_buf());
+        Ok(())
+    }
+
+    /// Block until something changes. Changes that arrive in quick
+    /// succession, such as the several events of a single save, are
+    /// returned together.
+    pub fn wait(&mut self) -> io::Result<Vec<Change>> {
+        let mut changes = Vec::new();
+        let mut events = self.inner.read()?;
+        loop {
+            for event in events {
+                let dir = match self.dirs.get(&event.wd) {
+                    Some(dir) => dir,
+                    None if event.overflow => {
+                        changes.push(Change::Overflow);
+                        continue;
+                    }
+                    None => continue,
+                };
+                if event.ignored {
+                    self.dirs.remove(&event.wd);
+                    continue;
+                }
+                let path = dir.join(&event.name);
+                let change = if event.removed {
+                    Change::Removed(path)
+                } else {
+                    Change::Modified(path)
+                };
+                if !changes.contains(&change) {
+                    changes.push(change);
+                }
+            }
+            if !self.inner.poll(sys::DEBOUNCE_MS)? {
+                break;
+            }
+            events = self.inner.read()?;
+        }
+        Ok(changes)
+    }
+}
+
+/// Whether a batch of changes calls for reloading config and ignore
+/// files, `conf_files` are the canonical paths of those read so far
+pub fn needs_reload(changes: &[Change], conf_files: &[PathBuf]) -> bool {
+    changes.iter().any(|change| match change {
+        Change::Modified(path) | Change::Removed(path) => {
+            path.file_name()
+                .is_some_and(|name| RELOAD_ON.iter().any(|r| name == *r))
+                || conf_files.iter().any(|c| is_conf_file(c, path))
+        }
+        Change::Overflow => true,
+    })
+}
+
+// changed paths are relative to the path their directory was watched
+// as, and may be gone already, so only their directory is canonicalized
+fn is_conf_file(conf_file: &Path, path: &Path) -> bool {
+    let dir = match path.parent() {
+        Some(dir) if dir.as_os_str().is_empty() => Path::new("."),
+        Some(dir) => dir,
+        None => return false,
+    };
+    match (fs::canonicalize(dir), path.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name) == conf_file,
+        _ => false,
+    }
+}
+
+/// `.nix` files under the target that were modified, along with
+/// paths that were removed. New directories are watched as they
+/// appear, and the files within them count as modified.
+pub fn nix_files(
+    watcher: &mut Watcher,
+    changes: Vec<Change>,
+    target: &Path,
+    ignore: &Ignore,
+) -> io::Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let is_nix = |path: &Path| matches!(path.extension(), Some(e) if e == "nix");
+    let mut modified = Vec::new();
+    let mut removed = Vec::new();
+    for change in changes {
+        match change {
+            // config files may be watched outside of the target
+            Change::Modified(path) if !path.starts_with(target) => (),
+            Change::Modified(path) if path.is_dir() && target.is_dir() => {
+                watcher.watch_tree(&path, ignore)?;
+                modified.extend(dirs::walk_nix_files(ignore, &path)?);
+            }
+            Change::Modified(path)
+                if (target.is_dir() || path == target)
+                    && is_nix(&path)
+                    && !ignore.matched(&path, false) =>
+            {
+                modified.push(path)
+            }
+            Change::Removed(path) => removed.push(path),
+            _ => (),
+        }
+    }
+    Ok((modified, removed))
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::{ffi::CString, io, mem, os::unix::ffi::OsStrExt, path::Path};
+
+    pub const DEBOUNCE_MS: i32 = 100;
+
+    const WATCH_MASK: u32 = libc::IN_CLOSE_WRITE
+        | libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO
+        | libc::IN_ONLYDIR;
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct Event {
+        pub wd: i32,
+        pub name: String,
+        pub removed: bool,
+        pub ignored: bool,
+        pub overflow: bool,
+    }
+
+    /// Events as the kernel packs them into a buffer, back to back and
+    /// with their names padded with nul bytes
+    pub fn parse_events(buffer: &[u8]) -> Vec<Event> {
+        let header = mem::size_of::<libc::inotify_event>();
+        let mut events = Vec::new();
+        let mut offset = 0;
+        while offset + header <= buffer.len() {
+            // events need not be aligned
+            let raw = unsafe {
+                std::ptr::read_unaligned(buffer[offset..].as_ptr() as *const libc::inotify_event)
+            };
+            let name_bytes = match buffer.get(offset + header..offset + header + raw.len as usize) {
+                Some(name_bytes) => name_bytes,
+                None => break,
+            };
+            let name = name_bytes
+                .split(|&b| b == 0)
+                .next()
+                .map(|n| String::from_utf8_lossy(n).into_owned())
+                .unwrap_or_default();
+            events.push(Event {
+                wd: raw.wd,
+                name,
+                removed: raw.mask & (libc::IN_DELETE | libc::IN_MOVED_FROM) != 0,
+                ignored: raw.mask & libc::IN_IGNORED != 0,
+                overflow: raw.mask & libc::IN_Q_OVERFLOW != 0,
+            });
+            offset += header + raw.len as usize;
+        }
+        events
+    }
+
+    pub struct Inotify {
+        fd: libc::c_int,
+    }
+
+    impl Inotify {
+        pub fn new() -> io::Result<Self> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { fd })
+        }
+
+        pub fn add_watch(&self, dir: &Path) -> io::Result<i32> {
+            let path = CString::new(dir.as_os_str().as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), WATCH_MASK) };
+            if wd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(wd)
+        }
+
+        /// Whether events are available within `timeout` milliseconds
+        pub fn poll(&self, timeout: i32) -> io::Result<bool> {
+            let mut pollfd = libc::pollfd {
+                fd: self.fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+                n if n < 0 => Err(io::Error::last_os_error()),
+                n => Ok(n > 0),
+            }
+        }
+
+        /// Blocks until at least one event is available
+        pub fn read(&self) -> io::Result<Vec<Event>> {
+            let mut buffer = [0u8; 4096];
+            let len = loop {
+                let n = unsafe {
+                    libc::read(
+                        self.fd,
+                        buffer.as_mut_ptr() as *mut libc::c_void,
+                        buffer.len(),
+                    )
+                };
+                if n >= 0 {
+                    break n as usize;
+                }
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            };
+            Ok(parse_events(&buffer[..len]))
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.fd) };
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::{io, path::Path};
+
+    pub const DEBOUNCE_MS: i32 = 100;
+
+    pub struct Event {
+        pub wd: i32,
+        pub name: String,
+        pub removed: bool,
+        pub ignored: bool,
+        pub overflow: bool,
+    }
+
+    pub struct Inotify;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "watch mode is only supported on linux",
+        )
+    }
+
+    impl Inotify {
+        pub fn new() -> io::Result<Self> {
+            Err(unsupported())
+        }
+        pub fn add_watch(&self, _dir: &Path) -> io::Result<i32> {
+            Err(unsupported())
+        }
+        pub fn poll(&self, _timeout: i32) -> io::Result<bool> {
+            Err(unsupported())
+        }
+        pub fn read(&self) -> io::Result<Vec<Event>> {
+            Err(unsupported())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tree;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_events() {
+        let event = |wd: i32, mask: u32, name: &str| {
+            // names are padded to a multiple of the header alignment
+            let len = if name.is_empty() {
+                0
+            } else {
+                (name.len() / 4 + 1) * 4
+            };
+            let mut bytes = Vec::new();
+            bytes.extend(wd.to_ne_bytes());
+            bytes.extend(mask.to_ne_bytes());
+            bytes.extend(0u32.to_ne_bytes());
+            bytes.extend((len as u32).to_ne_bytes());
+            bytes.extend(name.as_bytes());
+            bytes.resize(bytes.len() + len - name.len(), 0);
+            bytes
+        };
+        let buffer = [
+            event(1, libc::IN_CREATE, "default.nix"),
+            event(2, libc::IN_MOVED_FROM | libc::IN_ISDIR, "pkgs"),
+            event(2, libc::IN_IGNORED, ""),
+            event(-1, libc::IN_Q_OVERFLOW, ""),
+            // cut short, left out
+            event(1, libc::IN_DELETE, "flake.nix")[..20].to_vec(),
+        ]
+        .concat();
+        let events = sys::parse_events(&buffer);
+        let expected = |wd, name: &str, removed, ignored, overflow| sys::Event {
+            wd,
+            name: name.to_owned(),
+            removed,
+            ignored,
+            overflow,
+        };
+        assert_eq!(
+            events,
+            vec![
+                expected(1, "default.nix", false, false, false),
+                expected(2, "pkgs", true, false, false),
+                expected(2, "", false, true, false),
+                expected(-1, "", false, false, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn needs_reload() {
+        let root = tree("reload", &[("shared/base.toml", "")]);
+        let conf_files = [root.join("shared/base.toml")];
+        let reload = |change| super::needs_reload(&[change], &conf_files);
+
+        assert!(reload(Change::Modified("sub/statix.toml".into())));
+        assert!(reload(Change::Removed(".gitignore".into())));
+        assert!(reload(Change::Overflow));
+        // config files are told apart by path, not by name
+        assert!(reload(Change::Modified(
+            root.join("shared/../shared/base.toml")
+        )));
+        assert!(!reload(Change::Modified(root.join("base.toml"))));
+        assert!(!reload(Change::Modified("default.nix".into())));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn nix_files() {
+        use crate::{config::ConfFile, profile::Profiles};
+        use ignore::gitignore::GitignoreBuilder;
+
+        let root = tree(
+            "nix-files",
+            &[
+                ("target/new/a.nix", ""),
+                ("target/new/vendor/b.nix", ""),
+                ("target/new/c.txt", ""),
+                ("target/d.nix", ""),
+                ("target/vendor.nix", ""),
+                ("outside/e.nix", ""),
+            ],
+        );
+        let target = root.join("target");
+        let profiles = Profiles::with(ConfFile::default(), None, &[], &[], false).unwrap();
+        let mut gitignore = GitignoreBuilder::new(&target);
+        gitignore.add_line(None, "vendor*").unwrap();
+        let ignore = Ignore::new(gitignore.build().unwrap(), &profiles);
+        let mut watcher = Watcher::new().unwrap();
+
+        let changes = vec![
+            Change::Modified(target.join("new")),
+            Change::Modified(target.join("d.nix")),
+            Change::Modified(target.join("vendor.nix")),
+            Change::Modified(target.join("new/c.txt")),
+            Change::Modified(root.join("outside/e.nix")),
+            Change::Removed(target.join("gone")),
+        ];
+        let (modified, removed) =
+            super::nix_files(&mut watcher, changes, &target, &ignore).unwrap();
+
+        assert_eq!(
+            modified,
+            vec![target.join("new/a.nix"), target.join("d.nix")]
+        );
+        assert_eq!(removed, vec![target.join("gone")]);
+        // the new directory is watched, the ignored one within it is not
+        assert!(watcher.dirs.values().any(|dir| dir == &target.join("new")));
+        assert!(!watcher.dirs.values().any(|dir| dir.ends_with("vendor")));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
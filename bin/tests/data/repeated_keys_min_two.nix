@@ -0,0 +1,20 @@
+[
+  # fine
+  {
+    foo.bar = 1;
+    bar.foo = 2;
+  }
+
+  # exactly 2 occurrences
+  {
+    foo.bar = 1;
+    foo.baz = 2;
+  }
+
+  # 3 occurrences, as before
+  {
+    foo.bar = 1;
+    foo.bar."hello" = 1;
+    foo.again = 1;
+  }
+]
@@ -0,0 +1,23 @@
+[
+  (x: 0)
+  (_x: 0)
+  (x: y: x)
+  ({ config, lib, pkgs, ... }: pkgs.hello)
+  ({ lib, stdenv }: stdenv.mkDerivation { })
+  (
+    { lib
+    , stdenv
+    , fetchurl
+    , enableFoo ? lib.versionAtLeast stdenv.version "1"
+    }:
+    enableFoo
+  )
+  (args @ { a, ... }: a)
+  ({ a, b, ... } @ args: args)
+  # `_`-prefixed names are ignored
+  ({ a, _b, ... } @ _args: a)
+  # left to redundant_pattern_bind
+  ({ ... } @ args: null)
+  # shadowed, the outer argument is unused
+  (x: x: x)
+]
@@ -0,0 +1,50 @@
+[
+  (
+    let
+      a = 1;
+      b = 2;
+    in
+    b
+  )
+  (
+    # unused, even if referenced by itself
+    let
+      f = x: f (x + 1);
+      inherit (builtins) map filter;
+    in
+    filter (x: x) [ ]
+  )
+  (
+    # shadowed by the lambda argument
+    let
+      a = 1;
+    in
+    a: a
+  )
+  (
+    # `with` does not capture lexically bound names
+    let
+      a = 1;
+      b = 2;
+    in
+    with { b = 3; }; a + b
+  )
+  (
+    # used by a sibling binding
+    let
+      a = 1;
+      b = a;
+    in
+    b
+  )
+  (
+    # inherit refers to the outer scope
+    let
+      a = 1;
+    in
+    let
+      inherit a;
+    in
+    a
+  )
+]
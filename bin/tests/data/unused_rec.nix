@@ -0,0 +1,33 @@
+[
+  rec {
+    name = "statix";
+    src = ./.;
+  }
+
+  # refers to a sibling key
+  rec {
+    pname = "statix";
+    name = "${pname}-0.5.8";
+  }
+
+  # refers to an outer binding of the same name
+  (
+    let
+      version = "0.5.8";
+    in
+    {
+      inherit version;
+      passthru = rec { inherit version; };
+    }
+  )
+
+  # shadowed by a lambda argument
+  rec { f = a: a; a = 2; }
+
+  # string keys are in scope as well
+  rec { "foo" = 1; bar = foo; }
+  rec { ${"foo"} = 1; bar = foo; }
+
+  # dynamic keys may bind anything
+  (name: rec { ${name} = 1; bar = foo; })
+]
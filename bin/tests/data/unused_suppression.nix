@@ -0,0 +1,36 @@
+# statix: allow-file(W14)
+let
+  a = 2;
+  b = 3;
+in
+{
+  # suppressed by name
+  # statix: allow(manual_inherit)
+  a = a;
+
+  # suppressed by code, along with a reason
+  # statix: allow(W03) -- kept for symmetry with the other keys
+  b = b;
+
+  # suppressed for the file
+  c = { inherit; };
+
+  # nothing to suppress
+  # statix: allow(manual_inherit, eta_reduction)
+  d = 1;
+
+  # unknown lint
+  # statix: allow(manual_inheritance)
+  e = 2;
+
+  # malformed
+  # statix: allow manual_inherit
+  f = 3;
+
+  # misplaced
+  # statix: allow-file(W03)
+  g = 4;
+
+  # nothing follows
+  # statix: allow(W03)
+}
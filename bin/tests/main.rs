@@ -1,10 +1,21 @@
-use lib::session::{SessionInfo, Version};
+use lib::{
+    session::{SessionInfo, Version},
+    OptionValue,
+};
 
 macro_rules! session_info {
     ($version:expr) => {{
         let v: Version = $version.parse().unwrap();
         SessionInfo::from_version(v)
     }};
+    ($version:expr, $lint:literal.$option:literal = $value:expr) => {{
+        let options = std::iter::once((
+            $lint.to_owned(),
+            std::iter::once(($option.to_owned(), $value)).collect(),
+        ))
+        .collect();
+        session_info!($version).with_options(options)
+    }};
 }
 
 mod util {
@@ -66,5 +77,65 @@ test_lint! {
     bool_simplification,
     useless_has_attr,
     repeated_keys,
-    empty_list_concat
+    repeated_keys_min_two => session_info!("2.6", "repeated_keys"."min_occurrences" = OptionValue::Int(2)),
+    empty_list_concat,
+    unused_let_binding,
+    unused_argument,
+    unused_rec,
+    unused_suppression
+}
+
+mod formats {
+    use super::{SessionInfo, Version};
+    use statix::{config::OutFormat, lint, traits::WriteDiagnostic};
+    use vfs::ReadOnlyVfs;
+
+    // `contents` checked as the file at `path`, written in `format`
+    fn write(path: &str, contents: &str, format: OutFormat) -> String {
+        let vfs = ReadOnlyVfs::singleton(path, contents.as_bytes());
+        let session = session_info!("2.6");
+        let results = vfs
+            .iter()
+            .map(|entry| lint::lint(entry, &session))
+            .collect::<Vec<_>>();
+        let mut buffer = Vec::new();
+        buffer.write_results(&results, &vfs, format).unwrap();
+        String::from_utf8(buffer)
+            .unwrap()
+            .replace(env!("CARGO_PKG_VERSION"), "[version]")
+    }
+
+    // only the safe fix of `bool_comparison` is offered, the one of
+    // `unused_argument` is unsafe
+    #[cfg(feature = "json")]
+    #[test]
+    fn sarif() {
+        let out = write("data/fixes.nix", "{ a, b }: a == true\n", OutFormat::Sarif);
+        insta::assert_snapshot!(out);
+    }
+
+    // `<`, `&` and `"` in the message are escaped
+    const ESCAPED: &str = "(a < \"&b\") == true\n";
+
+    #[test]
+    fn checkstyle() {
+        insta::assert_snapshot!(write("data/escaped.nix", ESCAPED, OutFormat::Checkstyle));
+    }
+
+    #[test]
+    fn junit() {
+        insta::assert_snapshot!(write("data/escaped.nix", ESCAPED, OutFormat::Junit));
+    }
+
+    // `%`, `\r`, `\n`, `:` and `,` are escaped in the path, the
+    // message only needs the first three escaped
+    #[test]
+    fn github() {
+        let out = write(
+            "data/50%,a:b\r\n.nix",
+            "\"50%:a,\r\nb\" == true\n",
+            OutFormat::Github,
+        );
+        insta::assert_snapshot!(out);
+    }
 }
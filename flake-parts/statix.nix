@@ -28,7 +28,10 @@
             ];
           };
           cargoLock.lockFile = root + "/Cargo.lock";
-          buildFeatures = [ "json" ];
+          buildFeatures = [
+            "json"
+            "lsp"
+          ];
           RUSTFLAGS = "--deny warnings";
           nativeCheckInputs = [ pkgs.clippy ];
 
@@ -1,10 +1,14 @@
 #![recursion_limit = "1024"]
 mod lints;
 mod make;
+pub mod options;
+pub mod scope;
 pub mod session;
+pub mod suppression;
 mod utils;
 
 pub use lints::LINTS;
+pub use options::{LintOption, LintOptions, OptionType, OptionValue};
 use session::SessionInfo;
 
 use rnix::{parser::ParseError, SyntaxElement, SyntaxKind, TextRange};
@@ -16,17 +20,98 @@ use serde::{
     Serialize,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "json-out", derive(Serialize))]
 pub enum Severity {
+    #[default]
     Warn,
     Error,
     Hint,
 }
 
-impl Default for Severity {
-    fn default() -> Self {
-        Self::Warn
+/// Whether a fix can be applied without review. Levels are ordered
+/// from most to least safe.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "json-out", derive(Serialize))]
+#[cfg_attr(feature = "json-out", serde(rename_all = "kebab-case"))]
+pub enum Applicability {
+    /// Preserves the meaning of the code
+    #[default]
+    Safe,
+    /// May change the meaning of the code, applied on request
+    Unsafe,
+    /// Only shown, never applied
+    DisplayOnly,
+}
+
+impl Applicability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Safe => "safe",
+            Self::Unsafe => "unsafe",
+            Self::DisplayOnly => "display-only",
+        }
+    }
+}
+
+impl std::str::FromStr for Applicability {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "safe" => Ok(Self::Safe),
+            "unsafe" => Ok(Self::Unsafe),
+            "display-only" => Ok(Self::DisplayOnly),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Broad grouping of lints, lints can be enabled or disabled by
+/// category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "json-out", derive(Serialize))]
+#[cfg_attr(feature = "json-out", serde(rename_all = "lowercase"))]
+pub enum Category {
+    /// Code that could be written more idiomatically
+    Style,
+    /// Code that could be faster
+    Perf,
+    /// Code that is likely wrong
+    Correctness,
+    /// Code that relies on deprecated features
+    Deprecated,
+    /// Code that is more complex than it needs to be
+    Complexity,
+}
+
+impl Category {
+    pub const ALL: [Self; 5] = [
+        Self::Style,
+        Self::Perf,
+        Self::Correctness,
+        Self::Deprecated,
+        Self::Complexity,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Style => "style",
+            Self::Perf => "perf",
+            Self::Correctness => "correctness",
+            Self::Deprecated => "deprecated",
+            Self::Complexity => "complexity",
+        }
+    }
+}
+
+impl std::str::FromStr for Category {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|c| c.as_str() == s)
+            .copied()
+            .ok_or(())
     }
 }
 
@@ -74,6 +159,18 @@ impl Report {
         self.severity = severity;
         self
     }
+    /// Lower the applicability of every suggestion in this report
+    /// to at most `applicability`, see `Lint::check`
+    pub fn applicability(mut self, applicability: Applicability) -> Self {
+        for s in self
+            .diagnostics
+            .iter_mut()
+            .filter_map(|d| d.suggestion.as_mut())
+        {
+            s.applicability = s.applicability.max(applicability);
+        }
+        self
+    }
     /// A range that encompasses all the suggestions provided in this report
     pub fn total_suggestion_range(&self) -> Option<TextRange> {
         self.diagnostics
@@ -92,10 +189,21 @@ impl Report {
     pub fn range(&self) -> TextRange {
         self.total_suggestion_range().unwrap()
     }
-    /// Apply all diagnostics. Assumption: diagnostics do not overlap
+    /// All edits of all suggestions provided in this report
+    pub fn edits(&self) -> impl Iterator<Item = &Edit> {
+        self.diagnostics
+            .iter()
+            .filter_map(|d| d.suggestion.as_ref())
+            .flat_map(|s| s.edits.iter())
+    }
+    /// Apply all diagnostics. Assumption: edits do not overlap
     pub fn apply(&self, src: &mut String) {
-        for d in self.diagnostics.iter() {
-            d.apply(src);
+        let mut edits = self.edits().collect::<Vec<_>>();
+        // apply from the end of the source, so that applying one
+        // edit does not shift the ranges of the rest
+        edits.sort_by_key(|e| std::cmp::Reverse(e.at.start()));
+        for e in edits {
+            e.apply(src);
         }
     }
     /// Create a report out of a parse error
@@ -178,23 +286,93 @@ impl Serialize for Diagnostic {
     }
 }
 
-/// Suggested fix for a diagnostic, the fix is provided as a syntax element.
-/// Look at `make.rs` to construct fixes.
+/// Suggested fix for a diagnostic, made up of one or more edits that
+/// are applied together. Look at `make.rs` to construct fixes.
 #[derive(Debug)]
 pub struct Suggestion {
+    /// A range that encompasses all the edits
     pub at: TextRange,
-    pub fix: SyntaxElement,
+    pub edits: Vec<Edit>,
+    pub applicability: Applicability,
 }
 
 impl Suggestion {
-    /// Construct a suggestion.
+    /// Construct a suggestion that replaces a single range.
     pub fn new<E: Into<SyntaxElement>>(at: TextRange, fix: E) -> Self {
         Self {
             at,
-            fix: fix.into(),
+            edits: vec![Edit::new(at, fix)],
+            applicability: Applicability::default(),
         }
     }
+    /// Construct a suggestion out of several edits, such as moving an
+    /// expression elsewhere. Edits must not overlap.
+    pub fn with_edits(edits: Vec<Edit>) -> Self {
+        let at = edits
+            .iter()
+            .map(|e| e.at)
+            .reduce(|acc, next| acc.cover(next))
+            .expect("a suggestion needs at least one edit");
+        Self {
+            at,
+            edits,
+            applicability: Applicability::default(),
+        }
+    }
+    /// Set the applicability of this suggestion, suggestions are
+    /// never considered safer than the lint that made them
+    pub fn applicability(mut self, applicability: Applicability) -> Self {
+        self.applicability = applicability;
+        self
+    }
     /// Apply a suggestion to a source file
+    pub fn apply(&self, src: &mut String) {
+        let mut edits = self.edits.iter().collect::<Vec<_>>();
+        edits.sort_by_key(|e| std::cmp::Reverse(e.at.start()));
+        for e in edits {
+            e.apply(src);
+        }
+    }
+}
+
+unsafe impl Send for Suggestion {}
+
+#[cfg(feature = "json-out")]
+impl Serialize for Suggestion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_struct("Suggestion", 3)?;
+        let at = {
+            let start = usize::from(self.at.start());
+            let end = usize::from(self.at.end());
+            (start, end)
+        };
+        s.serialize_field("at", &at)?;
+        s.serialize_field("edits", &self.edits)?;
+        s.serialize_field("applicability", &self.applicability)?;
+        s.end()
+    }
+}
+
+/// Replacement of a single range, the replacement is provided as a
+/// syntax element, `make::empty` deletes the range.
+#[derive(Debug)]
+pub struct Edit {
+    pub at: TextRange,
+    pub fix: SyntaxElement,
+}
+
+impl Edit {
+    /// Construct an edit.
+    pub fn new<E: Into<SyntaxElement>>(at: TextRange, fix: E) -> Self {
+        Self {
+            at,
+            fix: fix.into(),
+        }
+    }
+    /// Apply an edit to a source file
     pub fn apply(&self, src: &mut String) {
         let start = usize::from(self.at.start());
         let end = usize::from(self.at.end());
@@ -202,15 +380,15 @@ impl Suggestion {
     }
 }
 
-unsafe impl Send for Suggestion {}
+unsafe impl Send for Edit {}
 
 #[cfg(feature = "json-out")]
-impl Serialize for Suggestion {
+impl Serialize for Edit {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("Suggestion", 2)?;
+        let mut s = serializer.serialize_struct("Edit", 2)?;
         let at = {
             let start = usize::from(self.at.start());
             let end = usize::from(self.at.end());
@@ -235,9 +413,28 @@ pub trait Metadata {
     fn name(&self) -> &'static str;
     fn note(&self) -> &'static str;
     fn code(&self) -> u32;
+    fn category(&self) -> Category;
     fn report(&self) -> Report;
     fn match_with(&self, with: &SyntaxKind) -> bool;
     fn match_kind(&self) -> Vec<SyntaxKind>;
+    /// Applicability of the suggestions made by this lint, set with
+    /// `applicability = Applicability::Unsafe` in the `lint` macro
+    fn applicability(&self) -> Applicability {
+        Applicability::Safe
+    }
+    /// Options this lint can be configured with, declared as fields of
+    /// the lint
+    fn options(&self) -> Vec<LintOption> {
+        Vec::new()
+    }
+    /// This lint with the options in `set` in place of the defaults,
+    /// `None` for lints without options
+    fn configure(
+        &self,
+        _set: &std::collections::BTreeMap<String, OptionValue>,
+    ) -> Option<Box<dyn std::any::Any + Send + Sync>> {
+        None
+    }
 }
 
 /// Contains offline explanation for each lint
@@ -254,7 +451,14 @@ pub trait Explain {
 
 /// Combines Rule and Metadata, do not implement manually, this is derived by
 /// the `lint` macro.
-pub trait Lint: Metadata + Explain + Rule + Send + Sync {}
+pub trait Lint: Metadata + Explain + Rule + Send + Sync {
+    /// Validate an element, suggestions in the report are made no
+    /// safer than the applicability of this lint
+    fn check(&self, node: &SyntaxElement, sess: &SessionInfo) -> Option<Report> {
+        self.validate(node, sess)
+            .map(|report| report.applicability(self.applicability()))
+    }
+}
 
 /// Helper utility to take lints from modules and insert them into a map for efficient
 /// access. Mapping is from a SyntaxKind to a list of lints that apply on that Kind.
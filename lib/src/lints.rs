@@ -20,5 +20,9 @@ lints! {
     bool_simplification,
     useless_has_attr,
     repeated_keys,
-    empty_list_concat
+    empty_list_concat,
+    unused_let_binding,
+    unused_argument,
+    unused_rec,
+    unused_suppression
 }
@@ -1,4 +1,4 @@
-use crate::{make, session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{make, session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -30,6 +30,7 @@ use rnix::{
     name = "bool_comparison",
     note = "Unnecessary comparison with boolean",
     code = 1,
+    category = Category::Complexity,
     match_with = SyntaxKind::NODE_BIN_OP
 )]
 struct BoolComparison;
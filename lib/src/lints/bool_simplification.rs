@@ -1,4 +1,4 @@
-use crate::{make, session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{make, session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -27,6 +27,7 @@ use rnix::{
     name = "bool_simplification",
     note = "This boolean expression can be simplified",
     code = 18,
+    category = Category::Complexity,
     match_with = SyntaxKind::NODE_UNARY_OP
 )]
 struct BoolSimplification;
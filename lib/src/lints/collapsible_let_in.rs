@@ -1,4 +1,4 @@
-use crate::{make, session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{make, session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -40,6 +40,7 @@ use rowan::Direction;
     name = "collapsible_let_in",
     note = "These let-in expressions are collapsible",
     code = 6,
+    category = Category::Complexity,
     match_with = SyntaxKind::NODE_LET_IN
 )]
 struct CollapsibleLetIn;
@@ -1,4 +1,4 @@
-use crate::{session::SessionInfo, Metadata, Report, Rule};
+use crate::{session::SessionInfo, Category, Metadata, Report, Rule};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -34,6 +34,7 @@ use rnix::{
     name = "deprecated_to_path",
     note = "Found usage of deprecated builtin toPath",
     code = 17,
+    category = Category::Deprecated,
     match_with = SyntaxKind::NODE_APPLY
 )]
 struct DeprecatedIsNull;
@@ -46,7 +47,7 @@ impl Rule for DeprecatedIsNull {
             if let NodeOrToken::Node(node) = node;
             if let Some(apply) = Apply::cast(node.clone());
             let lambda_path = apply.lambda()?.to_string();
-            if ALLOWED_PATHS.iter().any(|&p| p == lambda_path.as_str());
+            if ALLOWED_PATHS.contains(&lambda_path.as_str());
             then {
                 let at = node.text_range();
                 let message = format!("`{}` is deprecated, see `:doc builtins.toPath` within the REPL for more", lambda_path);
@@ -1,4 +1,4 @@
-use crate::{make, session::SessionInfo, utils, Metadata, Report, Rule, Suggestion};
+use crate::{make, session::SessionInfo, utils, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -24,6 +24,7 @@ use rnix::{
     name = "empty_inherit",
     note = "Found empty inherit statement",
     code = 14,
+    category = Category::Complexity,
     match_with = SyntaxKind::NODE_INHERIT
 )]
 struct EmptyInherit;
@@ -1,4 +1,4 @@
-use crate::{session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{session::SessionInfo, utils, Category, Metadata, Report, Rule};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -29,6 +29,7 @@ use rnix::{
     name = "empty_let_in",
     note = "Useless let-in expression",
     code = 2,
+    category = Category::Complexity,
     match_with = SyntaxKind::NODE_LET_IN
 )]
 struct EmptyLetIn;
@@ -44,20 +45,14 @@ impl Rule for EmptyLetIn {
             if entries.count() == 0;
             if inherits.count() == 0;
 
-            if let Some(body) = let_in_expr.body();
-
-            // ensure that the let-in-expr does not have comments
-            let has_comments = node
-                .children_with_tokens()
-                .any(|el| el.kind() == SyntaxKind::TOKEN_COMMENT);
+            if let_in_expr.body().is_some();
             then {
                 let at = node.text_range();
-                let replacement = body;
                 let message = "This let-in expression has no entries";
-                Some(if has_comments {
-                    self.report().diagnostic(at, message)
-                } else {
-                    self.report().suggest(at, message, Suggestion::new(at, replacement))
+                // keep the let-in expression around if it has comments
+                Some(match utils::collapse_let_in(&let_in_expr) {
+                    Some(suggestion) => self.report().suggest(at, message, suggestion),
+                    None => self.report().diagnostic(at, message),
                 })
             } else {
                 None
@@ -1,4 +1,4 @@
-use crate::{session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -27,6 +27,7 @@ use rnix::{
     name = "empty_list_concat",
     note = "Unnecessary concatenation with empty list",
     code = 23,
+    category = Category::Complexity,
     match_with = SyntaxKind::NODE_BIN_OP
 )]
 struct EmptyListConcat;
@@ -1,4 +1,4 @@
-use crate::{make, session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{make, session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -36,6 +36,7 @@ use rnix::{
     name = "empty_pattern",
     note = "Found empty pattern in function argument",
     code = 10,
+    category = Category::Style,
     match_with = SyntaxKind::NODE_LAMBDA
 )]
 struct EmptyPattern;
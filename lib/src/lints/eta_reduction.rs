@@ -1,10 +1,13 @@
-use crate::{session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{
+    scope::Resolution, session::SessionInfo, Applicability, Category, Metadata, Report, Rule,
+    Suggestion,
+};
 
 use if_chain::if_chain;
 use macros::lint;
 use rnix::{
-    types::{Apply, Ident, Lambda, TokenWrapper, TypedNode},
-    NodeOrToken, SyntaxElement, SyntaxKind, SyntaxNode,
+    types::{Apply, Ident, Lambda, TypedNode},
+    NodeOrToken, SyntaxElement, SyntaxKind,
 };
 
 /// ## What it does
@@ -33,22 +36,35 @@ use rnix::{
 /// in
 /// map double [ 1 2 3 ]
 /// ```
+///
+/// The fix is unsafe, `x: f x` is a function even if evaluating `f`
+/// fails, while `f` is not.
 #[lint(
     name = "eta_reduction",
     note = "This function expression is eta reducible",
     code = 7,
-    match_with = SyntaxKind::NODE_LAMBDA
+    category = Category::Style,
+    match_with = SyntaxKind::NODE_LAMBDA,
+    applicability = Applicability::Unsafe
 )]
-struct EtaReduction;
+struct EtaReduction {
+    /// Only report lambdas that apply a plain identifier, such as
+    /// `x: f x`, and leave `x: f a x` alone
+    #[option(default = true)]
+    only_bare_idents: bool,
+}
 
 impl Rule for EtaReduction {
-    fn validate(&self, node: &SyntaxElement, _sess: &SessionInfo) -> Option<Report> {
+    fn validate(&self, node: &SyntaxElement, sess: &SessionInfo) -> Option<Report> {
+        let only_bare_idents = self.configured(sess).only_bare_idents;
         if_chain! {
+            if let Some(scopes) = sess.scopes();
             if let NodeOrToken::Node(node) = node;
             if let Some(lambda_expr) = Lambda::cast(node.clone());
 
             if let Some(arg_node) = lambda_expr.arg();
             if let Some(arg) = Ident::cast(arg_node);
+            if let Some(arg_binding) = scopes.defined_by(&arg);
 
             if let Some(body_node) = lambda_expr.body();
             if let Some(body) = Apply::cast(body_node);
@@ -56,13 +72,13 @@ impl Rule for EtaReduction {
             if let Some(value_node) = body.value();
             if let Some(value) = Ident::cast(value_node);
 
-            if arg.as_str() == value.as_str();
+            if scopes.resolve(&value) == Some(Resolution::Static(arg_binding));
 
             if let Some(lambda_node) = body.lambda();
-            if !mentions_ident(&arg, &lambda_node);
-        // lambda body should be no more than a single Ident to
-        // retain code readability
-        if let Some(_) = Ident::cast(lambda_node);
+            if !scopes.is_referenced_within(arg_binding, lambda_node.text_range());
+            // lambda body should be no more than a single Ident to
+            // retain code readability, unless configured otherwise
+            if !only_bare_idents || Ident::cast(lambda_node).is_some();
 
             then {
                 let at = node.text_range();
@@ -79,11 +95,3 @@ impl Rule for EtaReduction {
         }
     }
 }
-
-fn mentions_ident(ident: &Ident, node: &SyntaxNode) -> bool {
-    if let Some(node_ident) = Ident::cast(node.clone()) {
-        node_ident.as_str() == ident.as_str()
-    } else {
-        node.children().any(|child| mentions_ident(ident, &child))
-    }
-}
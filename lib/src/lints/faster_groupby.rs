@@ -1,7 +1,7 @@
 use crate::{
     make,
     session::{SessionInfo, Version},
-    Metadata, Report, Rule, Suggestion,
+    Applicability, Category, Metadata, Report, Rule, Suggestion,
 };
 
 use if_chain::if_chain;
@@ -30,11 +30,16 @@ use rnix::{
 /// ```nix
 /// builtins.groupBy (x: if x > 2 then "big" else "small") [ 1 2 3 4 5 6 ];
 /// ```
+///
+/// The fix is unsafe, any attribute named `groupBy` is linted, not
+/// just the one from nixpkgs' lib.
 #[lint(
     name = "faster_groupby",
     note = "Found lib.groupBy",
     code = 15,
-    match_with = SyntaxKind::NODE_SELECT
+    category = Category::Perf,
+    match_with = SyntaxKind::NODE_SELECT,
+    applicability = Applicability::Unsafe
 )]
 struct FasterGroupBy;
 
@@ -1,7 +1,7 @@
 use crate::{
     make,
     session::{SessionInfo, Version},
-    Metadata, Report, Rule, Suggestion,
+    Applicability, Category, Metadata, Report, Rule, Suggestion,
 };
 
 use if_chain::if_chain;
@@ -30,11 +30,16 @@ use rnix::{
 /// ```nix
 /// builtins.zipAttrsWith (name: values: values) [ {a = "x";} {a = "y"; b = "z";} ]
 /// ```
+///
+/// The fix is unsafe, any attribute named `zipAttrsWith` is linted,
+/// not just the one from nixpkgs' lib.
 #[lint(
     name = "faster_zipattrswith",
     note = "Found lib.zipAttrsWith",
     code = 16,
-    match_with = SyntaxKind::NODE_SELECT
+    category = Category::Perf,
+    match_with = SyntaxKind::NODE_SELECT,
+    applicability = Applicability::Unsafe
 )]
 struct FasterZipAttrsWith;
 
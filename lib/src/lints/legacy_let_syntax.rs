@@ -1,4 +1,4 @@
-use crate::{make, session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{make, session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -39,6 +39,7 @@ use rnix::{
     name = "legacy_let_syntax",
     note = "Using undocumented `let` syntax",
     code = 5,
+    category = Category::Deprecated,
     match_with = SyntaxKind::NODE_LEGACY_LET
 )]
 struct ManualInherit;
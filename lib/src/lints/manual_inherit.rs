@@ -1,4 +1,4 @@
-use crate::{make, session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{make, session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -35,6 +35,7 @@ use rnix::{
     name = "manual_inherit",
     note = "Assignment instead of inherit",
     code = 3,
+    category = Category::Style,
     match_with = SyntaxKind::NODE_KEY_VALUE
 )]
 struct ManualInherit;
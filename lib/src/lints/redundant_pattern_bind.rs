@@ -1,4 +1,4 @@
-use crate::{session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -30,6 +30,7 @@ use rnix::{
     name = "redundant_pattern_bind",
     note = "Found redundant pattern bind in function argument",
     code = 11,
+    category = Category::Complexity,
     match_with = SyntaxKind::NODE_PATTERN
 )]
 struct RedundantPatternBind;
@@ -1,4 +1,4 @@
-use crate::{session::SessionInfo, Metadata, Report, Rule};
+use crate::{session::SessionInfo, Category, Metadata, Report, Rule};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -38,12 +38,19 @@ use rnix::{
     name = "repeated_keys",
     note = "Avoid repeated keys in attribute sets",
     code = 20,
+    category = Category::Style,
     match_with = SyntaxKind::NODE_KEY_VALUE
 )]
-struct RepeatedKeys;
+struct RepeatedKeys {
+    /// Report keys assigned at least this many times, values below 2
+    /// count as 2
+    #[option(default = 3)]
+    min_occurrences: usize,
+}
 
 impl Rule for RepeatedKeys {
-    fn validate(&self, node: &SyntaxElement, _sess: &SessionInfo) -> Option<Report> {
+    fn validate(&self, node: &SyntaxElement, sess: &SessionInfo) -> Option<Report> {
+        let min_occurrences = self.configured(sess).min_occurrences.max(2);
         if_chain! {
             if let NodeOrToken::Node(node) = node;
             if let Some(key_value) = KeyValue::cast(node.clone());
@@ -77,35 +84,35 @@ impl Rule for RepeatedKeys {
             }).collect::<Vec<_>>();
 
             if occurrences.first()?.0 == key.node().text_range();
-            if occurrences.len() >= 3;
+            if occurrences.len() >= min_occurrences;
 
             then {
-                let mut iter = occurrences.into_iter();
-
-                let (first_annotation, first_subkey) = iter.next().unwrap();
-                let first_message = format!("The key `{}` is first assigned here ...", first_component_ident.as_str());
-
-                let (second_annotation, second_subkey) = iter.next().unwrap();
-                let second_message = "... repeated here ...";
+                // at most three occurrences are pointed out
+                let remaining_occurrences = occurrences.len().saturating_sub(3);
+                let shown = &occurrences[..occurrences.len().min(3)];
+                let subkeys = shown
+                    .iter()
+                    .map(|(_, subkey)| format!("{}=...; ", subkey))
+                    .collect::<String>();
 
-                let (third_annotation, third_subkey) = iter.next().unwrap();
-                let third_message = {
-                    let remaining_occurrences = iter.count();
-                    let mut message = match remaining_occurrences {
-                        0 => "... and here.".to_string(),
-                        1 => "... and here (`1` occurrence omitted).".to_string(),
-                        n => format!("... and here (`{}` occurrences omitted).", n),
+                let mut report = self.report();
+                for (i, (annotation, _)) in shown.iter().enumerate() {
+                    let message = if i == 0 {
+                        format!("The key `{}` is first assigned here ...", first_component_ident.as_str())
+                    } else if i + 1 < shown.len() {
+                        "... repeated here ...".to_string()
+                    } else {
+                        let mut message = match remaining_occurrences {
+                            0 => "... and here.".to_string(),
+                            1 => "... and here (`1` occurrence omitted).".to_string(),
+                            n => format!("... and here (`{}` occurrences omitted).", n),
+                        };
+                        message.push_str(&format!(" Try `{} = {{ {}}}` instead.", first_component_ident.as_str(), subkeys));
+                        message
                     };
-                    message.push_str(&format!(" Try `{} = {{ {}=...; {}=...; {}=...; }}` instead.", first_component_ident.as_str(), first_subkey, second_subkey, third_subkey));
-                    message
-                };
-
-                Some(
-                    self.report()
-                    .diagnostic(first_annotation, first_message)
-                    .diagnostic(second_annotation, second_message)
-                    .diagnostic(third_annotation, third_message),
-                )
+                    report = report.diagnostic(*annotation, message);
+                }
+                Some(report)
             } else {
                 None
             }
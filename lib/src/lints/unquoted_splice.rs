@@ -1,4 +1,4 @@
-use crate::{make, session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{make, session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -35,6 +35,7 @@ use rnix::{
     name = "unquoted_splice",
     note = "Found unquoted splice expression",
     code = 9,
+    category = Category::Style,
     match_with = SyntaxKind::NODE_DYNAMIC
 )]
 struct UnquotedSplice;
@@ -1,4 +1,4 @@
-use crate::{make, session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{make, session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -41,6 +41,7 @@ use rnix::{types::TypedNode, NodeOrToken, SyntaxElement, SyntaxKind};
     name = "unquoted_uri",
     note = "Found unquoted URI expression",
     code = 12,
+    category = Category::Deprecated,
     match_with = SyntaxKind::TOKEN_URI
 )]
 struct UnquotedUri;
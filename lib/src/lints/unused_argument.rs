@@ -0,0 +1,231 @@
+use crate::{
+    make,
+    scope::{BindingId, Scopes},
+    session::SessionInfo,
+    Applicability, Category, Metadata, Report, Rule, Suggestion,
+};
+
+use if_chain::if_chain;
+use macros::lint;
+use rnix::{
+    types::{Ident, Lambda, Pattern, TokenWrapper, TypedNode},
+    NodeOrToken, SyntaxElement, SyntaxKind, SyntaxNode, TextRange,
+};
+
+/// ## What it does
+/// Checks for function arguments, pattern entries and pattern binds
+/// that are never used in the body of the function.
+///
+/// ## Why is this bad?
+/// Unused arguments make it harder to tell what a function actually
+/// depends on, NixOS modules in particular tend to accumulate them.
+///
+/// ## Example
+///
+/// ```nix
+/// { config, lib, pkgs, ... }: {
+///   environment.systemPackages = [ pkgs.hello ];
+/// }
+/// ```
+///
+/// Remove the unused pattern entries, the ellipsis ensures that
+/// callers can still pass them:
+///
+/// ```nix
+/// { pkgs, ... }: {
+///   environment.systemPackages = [ pkgs.hello ];
+/// }
+/// ```
+///
+/// Unused arguments that are not patterns are replaced with `_`:
+///
+/// ```nix
+/// map (x: 0) [ 1 2 3 ]
+/// ```
+///
+/// ```nix
+/// map (_: 0) [ 1 2 3 ]
+/// ```
+///
+/// Names starting with `_` are never reported. The fixes are unsafe,
+/// the function accepts other arguments afterwards, and
+/// `builtins.functionArgs` returns other names.
+#[lint(
+    name = "unused_argument",
+    note = "Unused function argument",
+    code = 25,
+    category = Category::Correctness,
+    match_with = SyntaxKind::NODE_LAMBDA,
+    applicability = Applicability::Unsafe
+)]
+struct UnusedArgument;
+
+impl Rule for UnusedArgument {
+    fn validate(&self, node: &SyntaxElement, sess: &SessionInfo) -> Option<Report> {
+        if_chain! {
+            if let NodeOrToken::Node(node) = node;
+            if let Some(lambda_expr) = Lambda::cast(node.clone());
+            if let Some(scopes) = sess.scopes();
+            if let Some(arg) = lambda_expr.arg();
+            then {
+                if let Some(ident) = Ident::cast(arg.clone()) {
+                    let id = scopes.defined_by(&ident)?;
+                    if !is_unused(scopes, id, &ident) {
+                        return None;
+                    }
+                    let at = ident.node().text_range();
+                    let message = format!("The argument `{}` is never used, use `_` instead", ident.as_str());
+                    let replacement = make::ident("_").node().clone();
+                    return Some(self.report().suggest(at, message, Suggestion::new(at, replacement)));
+                }
+                let pattern = Pattern::cast(arg)?;
+                let unused = |ident: &Ident| {
+                    matches!(scopes.defined_by(ident), Some(id) if is_unused(scopes, id, ident))
+                };
+                let unused_entries = pattern
+                    .entries()
+                    .filter_map(|entry| entry.name())
+                    .filter(unused)
+                    .collect::<Vec<_>>();
+                // `{ ... } @ args` is left to redundant_pattern_bind
+                let unused_bind = pattern
+                    .at()
+                    .filter(|_| pattern.entries().next().is_some())
+                    .filter(unused);
+
+                if unused_entries.is_empty() && unused_bind.is_none() {
+                    return None;
+                }
+
+                let mut report = self.report();
+                let fix = without_unused(&pattern, &unused_entries, unused_bind.is_some());
+                let mut fix = fix.map(|replacement| Suggestion::new(pattern.node().text_range(), replacement));
+
+                // the fix for the entire pattern goes on the first diagnostic
+                for ident in unused_entries.iter() {
+                    let at = ident.node().text_range();
+                    let message = format!("The argument `{}` is never used", ident.as_str());
+                    report = match fix.take() {
+                        Some(suggestion) => report.suggest(at, message, suggestion),
+                        None => report.diagnostic(at, message),
+                    };
+                }
+                if let Some(ident) = unused_bind {
+                    let at = ident.node().text_range();
+                    let message = format!("The pattern bind `{}` is never used", ident.as_str());
+                    report = match fix.take() {
+                        Some(suggestion) => report.suggest(at, message, suggestion),
+                        None => report.diagnostic(at, message),
+                    };
+                }
+                Some(report)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// `_`-prefixed names are ignored on purpose
+fn is_unused(scopes: &Scopes, id: BindingId, ident: &Ident) -> bool {
+    !ident.as_str().starts_with('_') && scopes.references(id).is_empty()
+}
+
+// rebuild the pattern without the unused entries and bind, preserving
+// the original layout, and ensuring that an ellipsis is present
+fn without_unused(pattern: &Pattern, unused: &[Ident], drop_bind: bool) -> Option<SyntaxNode> {
+    let node = pattern.node();
+    let base = node.text_range().start();
+    let unused = unused
+        .iter()
+        .map(|i| i.node().parent().map(|p| p.text_range()))
+        .collect::<Option<Vec<_>>>()?;
+
+    // entries and the ellipsis, along with whether they are to be removed
+    let items = node
+        .children_with_tokens()
+        .filter(|el| matches!(el.kind(), SyntaxKind::NODE_PAT_ENTRY | SyntaxKind::TOKEN_ELLIPSIS))
+        .map(|el| (el.text_range(), unused.contains(&el.text_range())))
+        .collect::<Vec<_>>();
+
+    let text = node.to_string();
+    let slice = |at: TextRange| &text[usize::from(at.start() - base)..usize::from(at.end() - base)];
+
+    let mut edits: Vec<(TextRange, String)> = Vec::new();
+    match items.iter().position(|(_, removed)| !removed) {
+        _ if unused.is_empty() => (),
+        // nothing left, only the ellipsis remains
+        None => {
+            let (first, _) = items.first()?;
+            let (last, _) = items.last()?;
+            edits.push((first.cover(*last), "...".into()));
+        }
+        Some(first_kept) => {
+            // leading entries are removed along with the separator after them,
+            // other entries are removed along with the separator before them
+            if first_kept > 0 {
+                let at = TextRange::new(items[0].0.start(), items[first_kept].0.start());
+                edits.push((at, String::new()));
+            }
+            for idx in first_kept + 1..items.len() {
+                if items[idx].1 {
+                    let at = TextRange::new(items[idx - 1].0.end(), items[idx].0.end());
+                    edits.push((at, String::new()));
+                }
+            }
+            if !pattern.ellipsis() {
+                // reuse the last separator to fit in with the rest of
+                // the pattern, for example, with leading commas
+                let separator = match items.as_slice() {
+                    [.., (second_last, _), (last, _)] => {
+                        Some(slice(TextRange::new(second_last.end(), last.start())))
+                    }
+                    _ => None,
+                }
+                .filter(|s| !s.contains('#'))
+                .unwrap_or(", ");
+                let end = items.last()?.0.end();
+                edits.push((TextRange::empty(end), format!("{}...", separator)));
+            }
+        }
+    }
+
+    if drop_bind {
+        let bind = node
+            .children()
+            .find(|n| n.kind() == SyntaxKind::NODE_PAT_BIND)?;
+        let whitespace = |el: Option<SyntaxElement>| el.filter(|e| e.kind() == SyntaxKind::TOKEN_WHITESPACE);
+        let at = if node.first_child_or_token().as_ref() == Some(&bind.clone().into()) {
+            // `args @ { ... }`
+            let end = whitespace(bind.next_sibling_or_token())
+                .map(|w| w.text_range().end())
+                .unwrap_or_else(|| bind.text_range().end());
+            TextRange::new(bind.text_range().start(), end)
+        } else {
+            // `{ ... } @ args`
+            let start = whitespace(bind.prev_sibling_or_token())
+                .map(|w| w.text_range().start())
+                .unwrap_or_else(|| bind.text_range().start());
+            TextRange::new(start, bind.text_range().end())
+        };
+        edits.push((at, String::new()));
+    }
+
+    // comments within removed ranges would be lost
+    let has_comments = node
+        .descendants_with_tokens()
+        .filter(|el| el.kind() == SyntaxKind::TOKEN_COMMENT)
+        .any(|el| edits.iter().any(|(at, _)| at.contains_range(el.text_range())));
+    if has_comments {
+        return None;
+    }
+
+    let mut fixed = text.clone();
+    edits.sort_by_key(|(at, _)| std::cmp::Reverse(at.start()));
+    for (at, replacement) in edits {
+        let start = usize::from(at.start() - base);
+        let end = usize::from(at.end() - base);
+        fixed.replace_range(start..end, &replacement);
+    }
+    Some(make::pattern(&fixed).node().clone())
+}
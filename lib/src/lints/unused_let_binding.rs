@@ -0,0 +1,174 @@
+use crate::{
+    make,
+    scope::{BindingId, Scopes},
+    session::SessionInfo,
+    utils, Category, Diagnostic, Metadata, Report, Rule, Suggestion,
+};
+
+use if_chain::if_chain;
+use macros::lint;
+use rnix::{
+    types::{EntryHolder, Ident, KeyValue, LetIn, TokenWrapper, TypedNode},
+    NodeOrToken, SyntaxElement, SyntaxKind, SyntaxNode, TextRange,
+};
+
+/// ## What it does
+/// Checks for bindings in `let-in` expressions that are never
+/// referenced, neither in the body nor in other bindings.
+///
+/// ## Why is this bad?
+/// Dead code, probably left over from a refactor.
+///
+/// ## Example
+///
+/// ```nix
+/// let
+///   pkgs = import <nixpkgs> {};
+///   lib = pkgs.lib;
+/// in
+///   pkgs.hello
+/// ```
+///
+/// Remove the unused binding:
+///
+/// ```nix
+/// let
+///   pkgs = import <nixpkgs> {};
+/// in
+///   pkgs.hello
+/// ```
+#[lint(
+    name = "unused_let_binding",
+    note = "Unused let binding",
+    code = 24,
+    category = Category::Correctness,
+    match_with = SyntaxKind::NODE_LET_IN
+)]
+struct UnusedLetBinding;
+
+impl Rule for UnusedLetBinding {
+    fn validate(&self, node: &SyntaxElement, sess: &SessionInfo) -> Option<Report> {
+        if_chain! {
+            if let NodeOrToken::Node(node) = node;
+            if let Some(let_in_expr) = LetIn::cast(node.clone());
+            if let Some(scopes) = sess.scopes();
+            let unused = unused_bindings(&let_in_expr, scopes);
+            if !unused.is_empty();
+            then {
+                let unused_count = unused
+                    .iter()
+                    .map(|(id, _)| id)
+                    .collect::<std::collections::HashSet<_>>()
+                    .len();
+                let total_count = scopes.bindings_of(node).count();
+
+                let mut diagnostics = unused
+                    .into_iter()
+                    .map(|(_, site)| site.diagnostic())
+                    .collect::<Vec<_>>();
+
+                // nothing would be left, get rid of the let-in entirely
+                if unused_count == total_count {
+                    if let Some(suggestion) = utils::collapse_let_in(&let_in_expr) {
+                        for d in diagnostics.iter_mut() {
+                            d.suggestion = None;
+                        }
+                        diagnostics[0].suggestion = Some(suggestion);
+                    }
+                }
+
+                let mut report = self.report();
+                report.diagnostics.extend(diagnostics);
+                Some(report)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+enum Site {
+    /// `a = 1;` or `a.b = 1;`
+    Entry(Ident, KeyValue),
+    /// `a` in `inherit a;`, along with whether all idents in that
+    /// `inherit` are unused
+    Inherited(Ident, SyntaxNode, bool),
+}
+
+impl Site {
+    fn diagnostic(self) -> Diagnostic {
+        let delete = |at: TextRange| Suggestion::new(at, make::empty().node().clone());
+        match self {
+            Site::Entry(ident, key_value) => {
+                let at = key_value.node().text_range();
+                let message = format!("`{}` is bound here but never used", ident.as_str());
+                let replacement_at = utils::with_preceeding_whitespace(key_value.node());
+                Diagnostic::suggest(at, message, delete(replacement_at))
+            }
+            Site::Inherited(ident, inherit, all_unused) => {
+                let at = ident.node().text_range();
+                let message = format!("`{}` is inherited here but never used", ident.as_str());
+                if !all_unused {
+                    let replacement_at = utils::with_preceeding_whitespace(ident.node());
+                    Diagnostic::suggest(at, message, delete(replacement_at))
+                } else if inherit.children().find_map(Ident::cast).map(|i| i.node().text_range())
+                    == Some(at)
+                {
+                    // drop the entire statement along with its first ident
+                    let replacement_at = utils::with_preceeding_whitespace(&inherit);
+                    Diagnostic::suggest(at, message, delete(replacement_at))
+                } else {
+                    Diagnostic::new(at, message)
+                }
+            }
+        }
+    }
+}
+
+fn unused_bindings(let_in_expr: &LetIn, scopes: &Scopes) -> Vec<(BindingId, Site)> {
+    let entries = let_in_expr
+        .entries()
+        .filter_map(|kv| {
+            let ident = kv.key()?.path().next().and_then(Ident::cast)?;
+            Some((scopes.defined_by(&ident)?, ident, kv))
+        })
+        .collect::<Vec<_>>();
+
+    // references from within a binding's own value do not count
+    let is_unused = |id: BindingId| {
+        scopes.references(id).iter().all(|r| {
+            entries
+                .iter()
+                .filter(|(other, _, _)| *other == id)
+                .any(|(_, _, kv)| kv.node().text_range().contains_range(*r))
+        })
+    };
+
+    let mut unused = entries
+        .iter()
+        // `_`-prefixed names are ignored on purpose
+        .filter(|(id, ident, _)| is_unused(*id) && !ident.as_str().starts_with('_'))
+        .map(|(id, ident, kv)| (*id, Site::Entry(ident.clone(), kv.clone())))
+        .collect::<Vec<_>>();
+
+    for inherit in let_in_expr.inherits() {
+        let idents = inherit
+            .idents()
+            .filter_map(|ident| Some((scopes.defined_by(&ident)?, ident)))
+            .collect::<Vec<_>>();
+        let unused_idents = idents
+            .iter()
+            .filter(|(id, ident)| is_unused(*id) && !ident.as_str().starts_with('_'))
+            .cloned()
+            .collect::<Vec<_>>();
+        let all_unused = unused_idents.len() == idents.len();
+        unused.extend(unused_idents.into_iter().map(|(id, ident)| {
+            (
+                id,
+                Site::Inherited(ident, inherit.node().clone(), all_unused),
+            )
+        }));
+    }
+
+    unused
+}
@@ -0,0 +1,82 @@
+use crate::{make, session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
+
+use if_chain::if_chain;
+use macros::lint;
+use rnix::{
+    types::{AttrSet, TypedNode},
+    NodeOrToken, SyntaxElement, SyntaxKind, TextRange,
+};
+
+/// ## What it does
+/// Checks for recursive attribute sets where no value refers to
+/// another key of the same set.
+///
+/// ## Why is this bad?
+/// The `rec` keyword is unnecessary here. Recursive sets bring all of
+/// their keys into scope, which is an easy way to accidentally shadow
+/// outer bindings and run into infinite recursion.
+///
+/// ## Example
+///
+/// ```nix
+/// rec {
+///   name = "statix";
+///   src = ./.;
+/// }
+/// ```
+///
+/// Remove the `rec` keyword:
+///
+/// ```nix
+/// {
+///   name = "statix";
+///   src = ./.;
+/// }
+/// ```
+#[lint(
+    name = "unused_rec",
+    note = "Unnecessary recursive attribute set",
+    code = 26,
+    category = Category::Complexity,
+    match_with = SyntaxKind::NODE_ATTR_SET
+)]
+struct UnusedRec;
+
+impl Rule for UnusedRec {
+    fn validate(&self, node: &SyntaxElement, sess: &SessionInfo) -> Option<Report> {
+        if_chain! {
+            if let NodeOrToken::Node(node) = node;
+            if let Some(attr_set) = AttrSet::cast(node.clone());
+            if attr_set.recursive();
+            if let Some(scopes) = sess.scopes();
+
+            // keys such as `${name}` may bind any name
+            if !scopes.is_opaque(node);
+
+            // no value refers to the keys of this set
+            if scopes
+                .bindings_of(node)
+                .all(|id| scopes.references(id).is_empty());
+
+            if let Some(rec_token) = node
+                .children_with_tokens()
+                .find(|el| el.kind() == SyntaxKind::TOKEN_REC);
+            then {
+                let at = rec_token.text_range();
+                let replacement_at = {
+                    let end = rec_token
+                        .next_sibling_or_token()
+                        .filter(|el| el.kind() == SyntaxKind::TOKEN_WHITESPACE)
+                        .map(|el| el.text_range().end())
+                        .unwrap_or_else(|| at.end());
+                    TextRange::new(at.start(), end)
+                };
+                let replacement = make::empty().node().clone();
+                let message = "No value in this set refers to another key, remove the `rec` keyword";
+                Some(self.report().suggest(at, message, Suggestion::new(replacement_at, replacement)))
+            } else {
+                None
+            }
+        }
+    }
+}
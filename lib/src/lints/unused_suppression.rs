@@ -0,0 +1,81 @@
+use crate::{
+    session::SessionInfo,
+    suppression::{self, Directive, DirectiveKind},
+    Category, Metadata, Report, Rule,
+};
+
+use if_chain::if_chain;
+use macros::lint;
+use rnix::{NodeOrToken, SyntaxElement, SyntaxKind};
+
+/// ## What it does
+/// Checks for `statix: allow(...)` and `statix: allow-file(...)`
+/// comments that do not suppress anything: malformed or misplaced
+/// comments, unknown lints, and lints that never fire on the
+/// suppressed expression.
+///
+/// ## Why is this bad?
+/// Stale suppressions hide future warnings for no reason.
+///
+/// ## Example
+///
+/// ```nix
+/// # statix: allow(manual_inherit)
+/// { a = b; }
+/// ```
+///
+/// Nothing is suppressed here, remove the comment:
+///
+/// ```nix
+/// { a = b; }
+/// ```
+#[lint(
+    name = "unused_suppression",
+    note = "Unused suppression",
+    code = 27,
+    category = Category::Style,
+    match_with = SyntaxKind::TOKEN_COMMENT
+)]
+struct UnusedSuppression;
+
+// suppressions that match no reports are found once all other lints
+// have run, see `suppression::Suppressions::unused`
+impl Rule for UnusedSuppression {
+    fn validate(&self, node: &SyntaxElement, _sess: &SessionInfo) -> Option<Report> {
+        if_chain! {
+            if let NodeOrToken::Token(token) = node;
+            if let Some(directive) = Directive::parse(token.text());
+            then {
+                let at = token.text_range();
+                let directive = match directive {
+                    Ok(directive) => directive,
+                    Err(()) => {
+                        let message = "Malformed suppression, expected `statix: allow(...)` or `statix: allow-file(...)`";
+                        return Some(self.report().diagnostic(at, message));
+                    }
+                };
+                if directive.scope(token).is_none() {
+                    let message = match directive.kind {
+                        DirectiveKind::Allow => "There is no expression after this suppression",
+                        DirectiveKind::AllowFile => "`allow-file` has no effect unless placed before the first expression of a file",
+                    };
+                    return Some(self.report().diagnostic(at, message));
+                }
+                let report = directive
+                    .lints
+                    .iter()
+                    .filter(|lint| suppression::resolve(lint).is_none())
+                    .fold(self.report(), |report, lint| {
+                        report.diagnostic(at, format!("`{}` is not a known lint", lint))
+                    });
+                if report.diagnostics.is_empty() {
+                    None
+                } else {
+                    Some(report)
+                }
+            } else {
+                None
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{make, session::SessionInfo, Metadata, Report, Rule, Suggestion};
+use crate::{make, session::SessionInfo, Category, Metadata, Report, Rule, Suggestion};
 
 use if_chain::if_chain;
 use macros::lint;
@@ -28,6 +28,7 @@ use rnix::{
     name = "useless_has_attr",
     note = "This `if` expression can be simplified with `or`",
     code = 19,
+    category = Category::Complexity,
     match_with = SyntaxKind::NODE_IF_ELSE
 )]
 struct UselessHasAttr;
@@ -1,10 +1,12 @@
-use crate::{session::SessionInfo, Diagnostic, Metadata, Report, Rule, Suggestion};
+use crate::{
+    make, session::SessionInfo, Category, Diagnostic, Edit, Metadata, Report, Rule, Suggestion,
+};
 
 use if_chain::if_chain;
 use macros::lint;
 use rnix::{
     types::{KeyValue, LetIn, Paren, ParsedType, TypedNode, Wrapper},
-    NodeOrToken, SyntaxElement, SyntaxKind,
+    NodeOrToken, SyntaxElement, SyntaxKind, SyntaxNode, TextRange,
 };
 
 /// ## What it does
@@ -36,21 +38,35 @@ use rnix::{
     name = "useless_parens",
     note = "These parentheses can be omitted",
     code = 8,
+    category = Category::Style,
     match_with = [
         SyntaxKind::NODE_KEY_VALUE,
         SyntaxKind::NODE_PAREN,
         SyntaxKind::NODE_LET_IN,
     ]
 )]
-struct UselessParens;
+struct UselessParens {
+    /// Expressions that need no parentheses around them: `list`,
+    /// `paren`, `string`, `attrset`, `select`, `ident` and `value`
+    /// (numbers, paths and URIs)
+    #[option(
+        default = ["list", "paren", "string", "attrset", "select", "ident"]
+            .iter()
+            .map(|kind| kind.to_string())
+            .collect(),
+        values = ["list", "paren", "string", "attrset", "select", "ident", "value"]
+    )]
+    primitives: Vec<String>,
+}
 
 impl Rule for UselessParens {
-    fn validate(&self, node: &SyntaxElement, _sess: &SessionInfo) -> Option<Report> {
+    fn validate(&self, node: &SyntaxElement, sess: &SessionInfo) -> Option<Report> {
+        let this = self.configured(sess);
         if_chain! {
             if let NodeOrToken::Node(node) = node;
             if let Some(parsed_type_node) = ParsedType::cast(node.clone());
 
-            if let Some(diagnostic) = do_thing(parsed_type_node);
+            if let Some(diagnostic) = do_thing(parsed_type_node, &this.primitives);
             then {
                 let mut report = self.report();
                 report.diagnostics.push(diagnostic);
@@ -62,7 +78,22 @@ impl Rule for UselessParens {
     }
 }
 
-fn do_thing(parsed_type_node: ParsedType) -> Option<Diagnostic> {
+// the name of a primitive expression, as given in the `primitives`
+// option
+fn primitive(parsed_type: &ParsedType) -> Option<&'static str> {
+    match parsed_type {
+        ParsedType::List(_) => Some("list"),
+        ParsedType::Paren(_) => Some("paren"),
+        ParsedType::Str(_) => Some("string"),
+        ParsedType::AttrSet(_) => Some("attrset"),
+        ParsedType::Select(_) => Some("select"),
+        ParsedType::Ident(_) => Some("ident"),
+        ParsedType::Value(_) => Some("value"),
+        _ => None,
+    }
+}
+
+fn do_thing(parsed_type_node: ParsedType, primitives: &[String]) -> Option<Diagnostic> {
     match parsed_type_node {
         ParsedType::KeyValue(kv) => if_chain! {
             if let Some(value_node) = kv.value();
@@ -72,8 +103,7 @@ fn do_thing(parsed_type_node: ParsedType) -> Option<Diagnostic> {
             then {
                 let at = value_range;
                 let message = "Useless parentheses around value in binding";
-                let replacement = inner;
-                Some(Diagnostic::suggest(at, message, Suggestion::new(at, replacement)))
+                Some(Diagnostic::suggest(at, message, unwrap(&value_in_parens, &inner)))
             } else {
                 None
             }
@@ -86,8 +116,7 @@ fn do_thing(parsed_type_node: ParsedType) -> Option<Diagnostic> {
             then {
                 let at = body_range;
                 let message = "Useless parentheses around body of `let` expression";
-                let replacement = inner;
-                Some(Diagnostic::suggest(at, message, Suggestion::new(at, replacement)))
+                Some(Diagnostic::suggest(at, message, unwrap(&body_as_parens, &inner)))
             } else {
                 None
             }
@@ -106,20 +135,12 @@ fn do_thing(parsed_type_node: ParsedType) -> Option<Diagnostic> {
 
             if let Some(inner_node) = paren_expr.inner();
             if let Some(parsed_inner) = ParsedType::cast(inner_node);
-            if matches!(
-                parsed_inner,
-                ParsedType::List(_)
-                | ParsedType::Paren(_)
-                | ParsedType::Str(_)
-                | ParsedType::AttrSet(_)
-                | ParsedType::Select(_)
-                | ParsedType::Ident(_)
-            );
+            if let Some(kind) = primitive(&parsed_inner);
+            if primitives.iter().any(|p| p == kind);
             then {
                 let at = paren_expr_range;
                 let message = "Useless parentheses around primitive expression";
-                let replacement = parsed_inner.node().clone();
-                Some(Diagnostic::suggest(at, message, Suggestion::new(at, replacement)))
+                Some(Diagnostic::suggest(at, message, unwrap(&paren_expr, parsed_inner.node())))
             } else {
                 None
             }
@@ -127,3 +148,15 @@ fn do_thing(parsed_type_node: ParsedType) -> Option<Diagnostic> {
         _ => None,
     }
 }
+
+// delete the parentheses and leave the inner expression untouched, so
+// that fixes within it can be applied alongside this one
+fn unwrap(paren: &Paren, inner: &SyntaxNode) -> Suggestion {
+    let outer = paren.node().text_range();
+    let inner = inner.text_range();
+    let delete = |at| Edit::new(at, make::empty().node().clone());
+    Suggestion::with_edits(vec![
+        delete(TextRange::new(outer.start(), inner.start())),
+        delete(TextRange::new(inner.end(), outer.end())),
+    ])
+}
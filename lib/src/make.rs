@@ -80,6 +80,10 @@ pub fn ident(text: &str) -> types::Ident {
     ast_from_text(text)
 }
 
+pub fn pattern(text: &str) -> types::Pattern {
+    ast_from_text(&format!("{}: null", text))
+}
+
 pub fn empty() -> types::Root {
     ast_from_text("")
 }
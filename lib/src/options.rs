@@ -0,0 +1,104 @@
+//! Options that lints can be configured with.
+//!
+//! Options are declared as fields of a lint, along with their
+//! defaults, see the `lint` macro. Values set by users reach rules
+//! through `SessionInfo`.
+
+use std::{collections::BTreeMap, convert::TryFrom, fmt};
+
+/// The value of an option, as set by users
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OptionValue {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    List(Vec<String>),
+}
+
+impl fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(b) => write!(f, "{}", b),
+            Self::Int(i) => write!(f, "{}", i),
+            Self::Str(s) => write!(f, "{:?}", s),
+            Self::List(l) => write!(f, "{:?}", l),
+        }
+    }
+}
+
+/// Values of options, by lint name and then by option name
+pub type LintOptions = BTreeMap<String, BTreeMap<String, OptionValue>>;
+
+/// Types that options can have
+pub trait OptionType: Sized {
+    /// The type, as described to users
+    const NAME: &'static str;
+    fn from_value(value: &OptionValue) -> Option<Self>;
+    fn to_value(&self) -> OptionValue;
+}
+
+impl OptionType for bool {
+    const NAME: &'static str = "boolean";
+    fn from_value(value: &OptionValue) -> Option<Self> {
+        match value {
+            OptionValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+    fn to_value(&self) -> OptionValue {
+        OptionValue::Bool(*self)
+    }
+}
+
+impl OptionType for usize {
+    const NAME: &'static str = "non-negative integer";
+    fn from_value(value: &OptionValue) -> Option<Self> {
+        match value {
+            OptionValue::Int(i) => usize::try_from(*i).ok(),
+            _ => None,
+        }
+    }
+    fn to_value(&self) -> OptionValue {
+        OptionValue::Int(i64::try_from(*self).unwrap_or(i64::MAX))
+    }
+}
+
+impl OptionType for String {
+    const NAME: &'static str = "string";
+    fn from_value(value: &OptionValue) -> Option<Self> {
+        match value {
+            OptionValue::Str(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+    fn to_value(&self) -> OptionValue {
+        OptionValue::Str(self.clone())
+    }
+}
+
+impl OptionType for Vec<String> {
+    const NAME: &'static str = "list of strings";
+    fn from_value(value: &OptionValue) -> Option<Self> {
+        match value {
+            OptionValue::List(l) => Some(l.clone()),
+            _ => None,
+        }
+    }
+    fn to_value(&self) -> OptionValue {
+        OptionValue::List(self.clone())
+    }
+}
+
+/// An option declared by a lint
+pub struct LintOption {
+    pub name: &'static str,
+    /// Taken from the doc comment of the field
+    pub doc: &'static str,
+    /// Name of the type of the option, see `OptionType::NAME`
+    pub ty: &'static str,
+    pub default: OptionValue,
+    /// Whether a value has the type of the option
+    pub accepts: fn(&OptionValue) -> bool,
+    /// The strings the option may hold, any if empty
+    pub values: &'static [&'static str],
+}
@@ -0,0 +1,575 @@
+//! Name resolution for nix expressions.
+//!
+//! `Scopes` is built once per file, it records every site that
+//! introduces a name (let-in, rec sets, lambda arguments, patterns and
+//! inherits) and resolves every identifier that is used as a variable
+//! to one of those sites.
+use std::collections::{HashMap, HashSet};
+
+use rnix::{
+    types::{
+        BinOp, BinOpKind, Dynamic, Ident, Inherit, KeyValue, Lambda, LetIn, Pattern, Select, Str,
+        TokenWrapper, TypedNode, With, Wrapper,
+    },
+    value::StrPart,
+    SyntaxKind, SyntaxNode, TextRange,
+};
+
+/// Cheap handle to a binding in `Scopes`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BindingId(usize);
+
+/// The kind of site that introduced a binding
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BindingKind {
+    /// `let a = 1; in ...`, also covers the legacy `let { ... }` syntax
+    LetIn,
+    /// `rec { a = 1; }`
+    RecAttrSet,
+    /// `a: ...`
+    LambdaArg,
+    /// `{ a, b ? 2 }: ...`
+    PatternEntry,
+    /// `{ ... } @ a: ...`
+    PatternBind,
+    /// `inherit a;` or `inherit (x) a;` within a let-in or a rec set
+    Inherit,
+}
+
+/// A name introduced by a binding site
+#[derive(Debug)]
+pub struct Binding {
+    /// The name being bound
+    pub name: String,
+    /// Range of the identifier, or of the string key, that first
+    /// defines this name
+    pub at: TextRange,
+    /// The kind of site that introduced this name
+    pub kind: BindingKind,
+    /// Range of the node that owns the scope: the let-in, rec set or lambda
+    pub owner: TextRange,
+}
+
+/// What an identifier refers to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// Lexically bound by a binding site
+    Static(BindingId),
+    /// Not lexically bound, but an enclosing `with`, or a set with
+    /// dynamic keys, may provide it
+    Dynamic,
+    /// Not bound at all, probably a builtin such as `map` or `true`
+    Free,
+}
+
+/// Name resolution model for a single file, see `Scopes::new`.
+#[derive(Debug, Default)]
+pub struct Scopes {
+    bindings: Vec<Binding>,
+    uses: Vec<Vec<TextRange>>,
+    definitions: HashMap<TextRange, BindingId>,
+    references: HashMap<TextRange, Resolution>,
+    opaque: HashSet<TextRange>,
+}
+
+impl Scopes {
+    /// Build the model by walking the tree under `root`
+    pub fn new(root: &SyntaxNode) -> Self {
+        let mut builder = Builder::default();
+        builder.visit(root);
+        builder.scopes
+    }
+    /// Resolve an identifier, returns `None` if this identifier is not
+    /// used as a variable, for example, keys in attribute sets
+    pub fn resolve(&self, ident: &Ident) -> Option<Resolution> {
+        self.references.get(&ident.node().text_range()).copied()
+    }
+    /// The binding that this identifier defines, if any
+    pub fn defined_by(&self, ident: &Ident) -> Option<BindingId> {
+        self.definitions.get(&ident.node().text_range()).copied()
+    }
+    pub fn binding(&self, id: BindingId) -> &Binding {
+        &self.bindings[id.0]
+    }
+    /// Ranges of all identifiers that resolve to this binding
+    pub fn references(&self, id: BindingId) -> &[TextRange] {
+        &self.uses[id.0]
+    }
+    /// Check if any identifier within `range` resolves to this binding
+    pub fn is_referenced_within(&self, id: BindingId, range: TextRange) -> bool {
+        self.references(id).iter().any(|r| range.contains_range(*r))
+    }
+    /// Check if a let-in or rec set has keys whose names are only known
+    /// once evaluated, such as `${name} = 1;`. Its bindings are then
+    /// not all known.
+    pub fn is_opaque(&self, owner: &SyntaxNode) -> bool {
+        self.opaque.contains(&owner.text_range())
+    }
+    /// All bindings introduced by a let-in, rec set or lambda
+    pub fn bindings_of<'a>(&'a self, owner: &SyntaxNode) -> impl Iterator<Item = BindingId> + 'a {
+        let owner = owner.text_range();
+        self.bindings
+            .iter()
+            .enumerate()
+            .filter(move |(_, b)| b.owner == owner)
+            .map(|(idx, _)| BindingId(idx))
+    }
+}
+
+enum Frame {
+    /// Names bound by a let-in, rec set or lambda, along with whether
+    /// dynamic keys may bind more of them
+    Names(HashMap<String, BindingId>, bool),
+    With,
+}
+
+#[derive(Default)]
+struct Builder {
+    scopes: Scopes,
+    stack: Vec<Frame>,
+}
+
+impl Builder {
+    fn visit(&mut self, node: &SyntaxNode) {
+        match node.kind() {
+            SyntaxKind::NODE_IDENT => self.reference(node),
+            SyntaxKind::NODE_LET_IN => {
+                self.enter_recursive(node, BindingKind::LetIn);
+                // entries have been visited already, be careful to not
+                // treat the last entry as a body in incomplete let-ins
+                if let Some(body) = LetIn::cast(node.clone())
+                    .and_then(|l| l.body())
+                    .filter(|b| {
+                        !matches!(
+                            b.kind(),
+                            SyntaxKind::NODE_KEY_VALUE | SyntaxKind::NODE_INHERIT
+                        )
+                    })
+                {
+                    self.visit(&body);
+                }
+                self.stack.pop();
+            }
+            SyntaxKind::NODE_LEGACY_LET => {
+                self.enter_recursive(node, BindingKind::LetIn);
+                self.stack.pop();
+            }
+            SyntaxKind::NODE_ATTR_SET
+                if node
+                    .children_with_tokens()
+                    .any(|el| el.kind() == SyntaxKind::TOKEN_REC) =>
+            {
+                self.enter_recursive(node, BindingKind::RecAttrSet);
+                self.stack.pop();
+            }
+            SyntaxKind::NODE_LAMBDA => {
+                let lambda = Lambda::cast(node.clone()).unwrap();
+                self.enter_lambda(&lambda);
+                if let Some(body) = lambda.body() {
+                    self.visit(&body);
+                }
+                self.stack.pop();
+            }
+            SyntaxKind::NODE_WITH => {
+                let with = With::cast(node.clone()).unwrap();
+                if let Some(namespace) = with.namespace() {
+                    self.visit(&namespace);
+                }
+                self.stack.push(Frame::With);
+                if let Some(body) = with.body() {
+                    self.visit(&body);
+                }
+                self.stack.pop();
+            }
+            SyntaxKind::NODE_KEY_VALUE => {
+                let key_value = KeyValue::cast(node.clone()).unwrap();
+                self.visit_key_value(&key_value);
+            }
+            SyntaxKind::NODE_INHERIT => {
+                let inherit = Inherit::cast(node.clone()).unwrap();
+                self.visit_inherit(&inherit);
+            }
+            SyntaxKind::NODE_SELECT => {
+                let select = Select::cast(node.clone()).unwrap();
+                if let Some(set) = select.set() {
+                    self.visit(&set);
+                }
+                if let Some(index) = select.index() {
+                    self.visit_attrpath(&index);
+                }
+            }
+            SyntaxKind::NODE_BIN_OP
+                if BinOp::cast(node.clone()).and_then(|b| b.operator())
+                    == Some(BinOpKind::IsSet) =>
+            {
+                let bin_op = BinOp::cast(node.clone()).unwrap();
+                if let Some(lhs) = bin_op.lhs() {
+                    self.visit(&lhs);
+                }
+                if let Some(rhs) = bin_op.rhs() {
+                    self.visit_attrpath(&rhs);
+                }
+            }
+            _ => {
+                for child in node.children() {
+                    self.visit(&child);
+                }
+            }
+        }
+    }
+
+    // pushes a frame containing all the names defined by the entries
+    // and inherits of a let-in, legacy let or rec set, and visits them
+    fn enter_recursive(&mut self, node: &SyntaxNode, kind: BindingKind) {
+        let owner = node.text_range();
+        let entries = node
+            .children()
+            .filter_map(KeyValue::cast)
+            .collect::<Vec<_>>();
+        let inherits = node
+            .children()
+            .filter_map(Inherit::cast)
+            .collect::<Vec<_>>();
+
+        // `inherit a;` refers to `a` from the enclosing scope
+        for inherit in inherits.iter().filter(|i| i.from().is_none()) {
+            self.visit_inherit(inherit);
+        }
+
+        let keys = entries
+            .iter()
+            .filter_map(|e| e.key()?.path().next())
+            .collect::<Vec<_>>();
+        let opaque = keys.iter().any(|k| key_name(k).is_none());
+        if opaque {
+            self.scopes.opaque.insert(owner);
+        }
+
+        self.stack.push(Frame::Names(HashMap::new(), opaque));
+        for key in keys.iter() {
+            if let Some(name) = key_name(key) {
+                self.define_name(&name, key.text_range(), kind, owner);
+            }
+        }
+        for inherit in inherits.iter() {
+            for ident in inherit.idents() {
+                self.define(&ident, BindingKind::Inherit, owner);
+            }
+        }
+
+        for entry in entries.iter() {
+            self.visit_key_value(entry);
+        }
+        for inherit in inherits.iter().filter(|i| i.from().is_some()) {
+            self.visit_inherit(inherit);
+        }
+    }
+
+    fn enter_lambda(&mut self, lambda: &Lambda) {
+        let owner = lambda.node().text_range();
+        self.stack.push(Frame::Names(HashMap::new(), false));
+        match lambda.arg() {
+            Some(arg) if arg.kind() == SyntaxKind::NODE_IDENT => {
+                let ident = Ident::cast(arg).unwrap();
+                self.define(&ident, BindingKind::LambdaArg, owner);
+            }
+            Some(arg) if arg.kind() == SyntaxKind::NODE_PATTERN => {
+                let pattern = Pattern::cast(arg).unwrap();
+                for entry in pattern.entries() {
+                    if let Some(ident) = entry.name() {
+                        self.define(&ident, BindingKind::PatternEntry, owner);
+                    }
+                }
+                if let Some(ident) = pattern.at() {
+                    self.define(&ident, BindingKind::PatternBind, owner);
+                }
+                // defaults may refer to other formals
+                for default in pattern.entries().filter_map(|e| e.default()) {
+                    self.visit(&default);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn visit_key_value(&mut self, key_value: &KeyValue) {
+        if let Some(key) = key_value.key() {
+            for component in key.path() {
+                self.visit_attrpath(&component);
+            }
+        }
+        if let Some(value) = key_value.value() {
+            self.visit(&value);
+        }
+    }
+
+    fn visit_inherit(&mut self, inherit: &Inherit) {
+        match inherit.from() {
+            Some(from) => {
+                if let Some(inner) = from.inner() {
+                    self.visit(&inner);
+                }
+            }
+            None => {
+                for ident in inherit.idents() {
+                    self.reference(ident.node());
+                }
+            }
+        }
+    }
+
+    // identifiers in attribute paths are names, not variables
+    fn visit_attrpath(&mut self, node: &SyntaxNode) {
+        match node.kind() {
+            SyntaxKind::NODE_IDENT => (),
+            SyntaxKind::NODE_SELECT => {
+                for child in node.children() {
+                    self.visit_attrpath(&child);
+                }
+            }
+            _ => self.visit(node),
+        }
+    }
+
+    fn define(&mut self, ident: &Ident, kind: BindingKind, owner: TextRange) {
+        self.define_name(ident.as_str(), ident.node().text_range(), kind, owner);
+    }
+
+    fn define_name(&mut self, name: &str, at: TextRange, kind: BindingKind, owner: TextRange) {
+        let frame = match self.stack.last_mut() {
+            Some(Frame::Names(names, _)) => names,
+            _ => return,
+        };
+        let scopes = &mut self.scopes;
+        let id = *frame.entry(name.to_owned()).or_insert_with(|| {
+            let id = BindingId(scopes.bindings.len());
+            scopes.bindings.push(Binding {
+                name: name.to_owned(),
+                at,
+                kind,
+                owner,
+            });
+            scopes.uses.push(Vec::new());
+            id
+        });
+        scopes.definitions.insert(at, id);
+    }
+
+    fn reference(&mut self, node: &SyntaxNode) {
+        let ident = match Ident::cast(node.clone()) {
+            Some(i) => i,
+            None => return,
+        };
+        let at = node.text_range();
+        let mut dynamic = false;
+        let mut resolution = None;
+        for frame in self.stack.iter().rev() {
+            match frame {
+                Frame::Names(names, opaque) => {
+                    if let Some(id) = names.get(ident.as_str()) {
+                        resolution = Some(Resolution::Static(*id));
+                        break;
+                    }
+                    dynamic |= opaque;
+                }
+                Frame::With => dynamic = true,
+            }
+        }
+        let resolution = resolution.unwrap_or(if dynamic {
+            Resolution::Dynamic
+        } else {
+            Resolution::Free
+        });
+        if let Resolution::Static(id) = resolution {
+            self.scopes.uses[id.0].push(at);
+        }
+        self.scopes.references.insert(at, resolution);
+    }
+}
+
+// the name bound by the first component of a key: identifiers, and
+// strings without interpolation, also within `${...}`. `None` if the
+// name is only known once evaluated
+fn key_name(node: &SyntaxNode) -> Option<String> {
+    match node.kind() {
+        SyntaxKind::NODE_IDENT => Ident::cast(node.clone()).map(|i| i.as_str().to_owned()),
+        SyntaxKind::NODE_DYNAMIC => Dynamic::cast(node.clone())?
+            .inner()
+            .filter(|inner| inner.kind() == SyntaxKind::NODE_STRING)
+            .and_then(|inner| key_name(&inner)),
+        SyntaxKind::NODE_STRING => Str::cast(node.clone())?
+            .parts()
+            .into_iter()
+            .map(|part| match part {
+                StrPart::Literal(literal) => Some(literal),
+                StrPart::Ast(_) => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_all(src: &str) -> (Scopes, Vec<(String, TextRange, Option<Resolution>)>) {
+        let root = rnix::parse(src).node();
+        let scopes = Scopes::new(&root);
+        let idents = root
+            .descendants()
+            .filter_map(Ident::cast)
+            .map(|i| {
+                (
+                    i.as_str().to_owned(),
+                    i.node().text_range(),
+                    scopes.resolve(&i),
+                )
+            })
+            .collect();
+        (scopes, idents)
+    }
+
+    fn resolution_of(src: &str, nth: usize) -> Option<Resolution> {
+        resolve_all(src).1[nth].2
+    }
+
+    #[test]
+    fn let_in() {
+        let src = "let a = 1; in a";
+        let (scopes, idents) = resolve_all(src);
+        // `a` in key position is not a reference
+        assert_eq!(idents[0].2, None);
+        match idents[1].2 {
+            Some(Resolution::Static(id)) => {
+                let binding = scopes.binding(id);
+                assert_eq!(binding.name, "a");
+                assert_eq!(binding.kind, BindingKind::LetIn);
+                assert_eq!(binding.at, idents[0].1);
+            }
+            r => panic!("expected static resolution, found {:?}", r),
+        }
+    }
+
+    #[test]
+    fn shadowing() {
+        let src = "let a = 1; in a: a";
+        let (scopes, idents) = resolve_all(src);
+        match idents[2].2 {
+            Some(Resolution::Static(id)) => {
+                assert_eq!(scopes.binding(id).kind, BindingKind::LambdaArg)
+            }
+            r => panic!("expected static resolution, found {:?}", r),
+        }
+    }
+
+    #[test]
+    fn with_is_dynamic() {
+        assert_eq!(
+            resolution_of("with pkgs; hello", 1),
+            Some(Resolution::Dynamic)
+        );
+        assert_eq!(resolution_of("hello", 0), Some(Resolution::Free));
+    }
+
+    #[test]
+    fn with_does_not_shadow() {
+        let (scopes, idents) = resolve_all("a: with pkgs; a");
+        match idents[2].2 {
+            Some(Resolution::Static(id)) => assert_eq!(scopes.binding(id).name, "a"),
+            r => panic!("expected static resolution, found {:?}", r),
+        }
+    }
+
+    #[test]
+    fn rec_set() {
+        let (_, idents) = resolve_all("rec { a = 1; b = a; }");
+        assert!(matches!(idents[2].2, Some(Resolution::Static(_))));
+        let (_, idents) = resolve_all("{ a = 1; b = a; }");
+        assert_eq!(idents[2].2, Some(Resolution::Free));
+    }
+
+    #[test]
+    fn string_keys() {
+        let (scopes, idents) = resolve_all(r#"rec { "a" = 1; b = a; }"#);
+        match idents[1].2 {
+            Some(Resolution::Static(id)) => {
+                let binding = scopes.binding(id);
+                assert_eq!(binding.name, "a");
+                assert_eq!(binding.kind, BindingKind::RecAttrSet);
+            }
+            r => panic!("expected static resolution, found {:?}", r),
+        }
+        let (_, idents) = resolve_all(r#"let "a b" = 1; in a"#);
+        assert_eq!(idents[0].2, Some(Resolution::Free));
+    }
+
+    #[test]
+    fn interpolated_string_keys() {
+        let (scopes, idents) = resolve_all(r#"let ${"a"} = 1; in a"#);
+        match idents[0].2 {
+            Some(Resolution::Static(id)) => assert_eq!(scopes.binding(id).name, "a"),
+            r => panic!("expected static resolution, found {:?}", r),
+        }
+    }
+
+    fn is_opaque(src: &str) -> bool {
+        let root = rnix::parse(src).node();
+        let set = root
+            .descendants()
+            .find(|n| n.kind() == SyntaxKind::NODE_ATTR_SET)
+            .unwrap();
+        Scopes::new(&root).is_opaque(&set)
+    }
+
+    #[test]
+    fn dynamic_keys_are_opaque() {
+        let src = r#"x: rec { ${x} = 1; "${x}b" = 2; c = 3; d = c + e; }"#;
+        assert!(is_opaque(src));
+        let (_, idents) = resolve_all(src);
+        let resolution = |name: &str| idents.iter().rev().find(|i| i.0 == name).unwrap().2;
+        // names the set does bind are still known
+        assert!(matches!(resolution("c"), Some(Resolution::Static(_))));
+        assert_eq!(resolution("e"), Some(Resolution::Dynamic));
+
+        assert!(!is_opaque(r#"rec { "a" = 1; ${"b"} = 2; }"#));
+    }
+
+    #[test]
+    fn inherit_refers_to_outer_scope() {
+        let src = "a: let inherit a; in a";
+        let (scopes, idents) = resolve_all(src);
+        match (idents[1].2, idents[2].2) {
+            (Some(Resolution::Static(outer)), Some(Resolution::Static(inner))) => {
+                assert_eq!(scopes.binding(outer).kind, BindingKind::LambdaArg);
+                assert_eq!(scopes.binding(inner).kind, BindingKind::Inherit);
+            }
+            r => panic!("expected static resolutions, found {:?}", r),
+        }
+    }
+
+    #[test]
+    fn pattern() {
+        let src = "{ a, b ? a } @ args: args.b";
+        let (scopes, idents) = resolve_all(src);
+        let kind_of = |r: Option<Resolution>| match r {
+            Some(Resolution::Static(id)) => Some(scopes.binding(id).kind),
+            _ => None,
+        };
+        assert_eq!(kind_of(idents[2].2), Some(BindingKind::PatternEntry));
+        assert_eq!(kind_of(idents[4].2), Some(BindingKind::PatternBind));
+        // attribute names in a select are not references
+        assert_eq!(idents[5].2, None);
+    }
+
+    #[test]
+    fn attrpaths() {
+        let (_, idents) = resolve_all("x: x ? a.b || x.${y} or z");
+        let names = idents
+            .iter()
+            .filter(|(_, _, r)| r.is_some())
+            .map(|(n, _, _)| n.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["x", "x", "y", "z"]);
+    }
+}
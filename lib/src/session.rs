@@ -1,4 +1,14 @@
-use std::{cmp::Ordering, str::FromStr};
+use std::{
+    any::Any,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{scope::Scopes, LintOptions, OptionValue, LINTS};
+
+use rnix::SyntaxNode;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Version {
@@ -50,16 +60,73 @@ impl FromStr for Version {
 #[non_exhaustive]
 pub struct SessionInfo {
     nix_version: Version,
+    scopes: Option<Scopes>,
+    options: Arc<LintOptions>,
+    // lints with options set, by name, see `Metadata::configure`
+    configured: Arc<HashMap<&'static str, Box<dyn Any + Send + Sync>>>,
 }
 
 impl SessionInfo {
     pub fn from_version(nix_version: Version) -> Self {
-        Self { nix_version }
+        Self {
+            nix_version,
+            scopes: None,
+            options: Arc::default(),
+            configured: Arc::default(),
+        }
+    }
+
+    /// Session with options set for lints, options that are not set
+    /// keep their defaults
+    pub fn with_options(self, options: LintOptions) -> Self {
+        let configured = LINTS
+            .iter()
+            .filter_map(|lint| {
+                let set = options.get(lint.name())?;
+                Some((lint.name(), lint.configure(set)?))
+            })
+            .collect();
+        Self {
+            options: Arc::new(options),
+            configured: Arc::new(configured),
+            ..self
+        }
+    }
+
+    /// Session for linting a single file, carries name resolution
+    /// information for the tree under `root`
+    pub fn with_scopes(&self, root: &SyntaxNode) -> Self {
+        Self {
+            nix_version: self.nix_version,
+            scopes: Some(Scopes::new(root)),
+            options: Arc::clone(&self.options),
+            configured: Arc::clone(&self.configured),
+        }
     }
 
     pub fn version(&self) -> &Version {
         &self.nix_version
     }
+
+    pub fn options(&self) -> &LintOptions {
+        &self.options
+    }
+
+    /// Options set for the lint named `lint`, if any
+    pub fn options_of(&self, lint: &str) -> Option<&BTreeMap<String, OptionValue>> {
+        self.options.get(lint)
+    }
+
+    /// The lint named `lint` with its options set, if any are
+    pub fn configured(&self, lint: &str) -> Option<&(dyn Any + Send + Sync)> {
+        self.configured.get(lint).map(|lint| &**lint)
+    }
+
+    /// Name resolution information, available only on sessions created
+    /// with `SessionInfo::with_scopes`
+    pub fn scopes(&self) -> Option<&Scopes> {
+        self.scopes.as_ref()
+    }
 }
 
 #[cfg(test)]
@@ -85,6 +152,23 @@ mod tests {
         assert!(v2 > v1);
     }
 
+    #[test]
+    fn configured_once() {
+        let options = std::iter::once((
+            "repeated_keys".to_owned(),
+            std::iter::once(("min_occurrences".to_owned(), OptionValue::Int(2))).collect(),
+        ))
+        .collect();
+        let sess = SessionInfo::from_version("2.4".parse().unwrap()).with_options(options);
+        let file = sess.with_scopes(&rnix::parse("{ }").node());
+        // sessions of files share the lints configured for the run
+        assert!(std::ptr::addr_eq(
+            sess.configured("repeated_keys").unwrap(),
+            file.configured("repeated_keys").unwrap()
+        ));
+        assert!(sess.configured("useless_parens").is_none());
+    }
+
     #[test]
     fn compare() {
         let v1 = "1.7".parse::<Version>().ok();
@@ -0,0 +1,297 @@
+//! Inline suppressions.
+//!
+//! `# statix: allow(manual_inherit, W08)` silences the listed lints on
+//! the expression that follows the comment, `# statix: allow-file(W04)`
+//! placed before the first expression of a file silences them in the
+//! entire file. Lints may be referred to by name or by code.
+
+use crate::{Report, LINTS};
+
+use rnix::{NodeOrToken, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, TextRange};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectiveKind {
+    /// `statix: allow(...)`
+    Allow,
+    /// `statix: allow-file(...)`
+    AllowFile,
+}
+
+/// A comment of the form `statix: allow(...)`
+#[derive(Debug)]
+pub struct Directive {
+    pub kind: DirectiveKind,
+    /// Lint names or codes, as written
+    pub lints: Vec<String>,
+}
+
+impl Directive {
+    /// Parse the text of a comment token. Regular comments produce `None`,
+    /// malformed directives produce an error.
+    pub fn parse(comment: &str) -> Option<Result<Self, ()>> {
+        let body = if let Some(block) = comment.strip_prefix("/*") {
+            block.strip_suffix("*/").unwrap_or(block)
+        } else {
+            comment.trim_start_matches('#')
+        };
+        let body = body.trim().strip_prefix("statix:")?.trim_start();
+        Some(Self::parse_body(body).ok_or(()))
+    }
+
+    // anything after the closing parenthesis is left for the
+    // reader, for example, the reason behind the suppression
+    fn parse_body(body: &str) -> Option<Self> {
+        let (kind, rest) = if let Some(rest) = body.strip_prefix("allow-file(") {
+            (DirectiveKind::AllowFile, rest)
+        } else if let Some(rest) = body.strip_prefix("allow(") {
+            (DirectiveKind::Allow, rest)
+        } else {
+            return None;
+        };
+        let (list, _) = rest.split_once(')')?;
+        let lints = list
+            .split(',')
+            .map(|l| l.trim().to_owned())
+            .collect::<Vec<_>>();
+        if lints.iter().any(String::is_empty) {
+            return None;
+        }
+        Some(Self { kind, lints })
+    }
+
+    /// The range this directive applies to, if any. `allow` applies
+    /// to the next node, tokens such as `}` or `;` are skipped and
+    /// there is nothing to apply to if no node follows. `allow-file`
+    /// applies to the entire file if it is placed before the first
+    /// expression.
+    pub fn scope(&self, comment: &SyntaxToken) -> Option<TextRange> {
+        let is_trivia = |el: &SyntaxElement| {
+            matches!(
+                el.kind(),
+                SyntaxKind::TOKEN_WHITESPACE | SyntaxKind::TOKEN_COMMENT
+            )
+        };
+        match self.kind {
+            DirectiveKind::Allow => std::iter::successors(comment.next_sibling_or_token(), |el| {
+                el.next_sibling_or_token()
+            })
+            .find_map(NodeOrToken::into_node)
+            .map(|node| node.text_range()),
+            DirectiveKind::AllowFile => {
+                let root = comment.parent();
+                let at_top = root.kind() == SyntaxKind::NODE_ROOT
+                    && std::iter::successors(comment.prev_sibling_or_token(), |el| {
+                        el.prev_sibling_or_token()
+                    })
+                    .all(|el| is_trivia(&el));
+                if at_top {
+                    Some(root.text_range())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Resolve a lint name, such as `manual_inherit`, or a lint code,
+/// such as `W03`, to a lint code
+pub fn resolve(lint: &str) -> Option<u32> {
+    let code = lint
+        .strip_prefix(|c| c == 'W' || c == 'w')
+        .and_then(|code| code.parse::<u32>().ok());
+    LINTS
+        .iter()
+        .find(|l| l.name() == lint || Some(l.code()) == code)
+        .map(|l| l.code())
+}
+
+struct Entry {
+    comment: TextRange,
+    scope: TextRange,
+    lint: String,
+    code: u32,
+    used: bool,
+}
+
+/// All well-formed suppressions in a file, along with whether they
+/// were used. Malformed or misplaced suppressions are left to the
+/// `unused_suppression` lint.
+pub struct Suppressions {
+    entries: Vec<Entry>,
+}
+
+impl Suppressions {
+    pub fn new(root: &SyntaxNode) -> Self {
+        let entries = root
+            .descendants_with_tokens()
+            .filter_map(|el| match el {
+                NodeOrToken::Token(token) if token.kind() == SyntaxKind::TOKEN_COMMENT => {
+                    Some(token)
+                }
+                _ => None,
+            })
+            .filter_map(|comment| {
+                let directive = Directive::parse(comment.text())?.ok()?;
+                let scope = directive.scope(&comment)?;
+                let comment = comment.text_range();
+                Some(directive.lints.into_iter().filter_map(move |lint| {
+                    Some(Entry {
+                        comment,
+                        scope,
+                        code: resolve(&lint)?,
+                        lint,
+                        used: false,
+                    })
+                }))
+            })
+            .flatten()
+            .collect();
+        Self { entries }
+    }
+
+    /// Remove suppressed diagnostics from the reports, reports left
+    /// without diagnostics are dropped. Suggestions that would touch a
+    /// suppressed diagnostic are dropped too.
+    pub fn apply<I: IntoIterator<Item = Report>>(&mut self, reports: I) -> Vec<Report> {
+        reports
+            .into_iter()
+            .filter_map(|mut report| {
+                let code = report.code;
+                let (suppressed, mut kept): (Vec<_>, Vec<_>) =
+                    std::mem::take(&mut report.diagnostics)
+                        .into_iter()
+                        .partition(|d| self.suppresses(code, d.at));
+                if kept.is_empty() {
+                    return None;
+                }
+                for diagnostic in kept.iter_mut() {
+                    let touches_suppressed = diagnostic
+                        .suggestion
+                        .as_ref()
+                        .is_some_and(|s| suppressed.iter().any(|d| s.at.intersect(d.at).is_some()));
+                    if touches_suppressed {
+                        diagnostic.suggestion = None;
+                    }
+                }
+                report.diagnostics = kept;
+                Some(report)
+            })
+            .collect()
+    }
+
+    fn suppresses(&mut self, code: u32, at: TextRange) -> bool {
+        let mut suppressed = false;
+        for entry in self.entries.iter_mut() {
+            if entry.code == code && entry.scope.contains_range(at) {
+                entry.used = true;
+                suppressed = true;
+            }
+        }
+        suppressed
+    }
+
+    /// Reports for suppressions that silenced nothing. Suppressions of
+    /// lints that are not `enabled` are skipped, they could not have
+    /// silenced anything.
+    pub fn unused<F: Fn(u32) -> bool>(&self, enabled: F) -> Vec<Report> {
+        let lint = match LINTS.iter().find(|l| l.name() == "unused_suppression") {
+            Some(lint) if enabled(lint.code()) => lint,
+            _ => return Vec::new(),
+        };
+        let mut reports: Vec<(TextRange, Report)> = Vec::new();
+        for entry in self.entries.iter().filter(|e| !e.used && enabled(e.code)) {
+            let message = format!(
+                "`{}` is allowed here, but nothing was suppressed",
+                entry.lint
+            );
+            match reports.last_mut() {
+                Some((comment, report)) if *comment == entry.comment => {
+                    report
+                        .diagnostics
+                        .push(crate::Diagnostic::new(entry.comment, message));
+                }
+                _ => reports.push((
+                    entry.comment,
+                    lint.report().diagnostic(entry.comment, message),
+                )),
+            }
+        }
+        reports.into_iter().map(|(_, report)| report).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directive(comment: &str) -> Option<Result<Directive, ()>> {
+        Directive::parse(comment)
+    }
+
+    #[test]
+    fn regular_comments() {
+        assert!(directive("# just a comment").is_none());
+        assert!(directive("/* statix */").is_none());
+    }
+
+    #[test]
+    fn allow() {
+        let d = directive("# statix: allow(manual_inherit, W08)")
+            .unwrap()
+            .unwrap();
+        assert_eq!(d.kind, DirectiveKind::Allow);
+        assert_eq!(d.lints, vec!["manual_inherit", "W08"]);
+    }
+
+    #[test]
+    fn allow_file() {
+        let d = directive("/* statix: allow-file(W04) */").unwrap().unwrap();
+        assert_eq!(d.kind, DirectiveKind::AllowFile);
+        assert_eq!(d.lints, vec!["W04"]);
+    }
+
+    #[test]
+    fn trailing_reason() {
+        let d = directive("# statix: allow(eta_reduction) -- keeps the arity explicit");
+        assert!(matches!(d, Some(Ok(_))));
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(
+            directive("# statix: allow manual_inherit"),
+            Some(Err(()))
+        ));
+        assert!(matches!(directive("# statix: allow()"), Some(Err(()))));
+        assert!(matches!(directive("# statix: alow(W04)"), Some(Err(()))));
+    }
+
+    #[test]
+    fn resolution() {
+        assert_eq!(resolve("manual_inherit"), Some(3));
+        assert_eq!(resolve("W03"), Some(3));
+        assert_eq!(resolve("w3"), Some(3));
+        assert_eq!(resolve("manual_inheritance"), None);
+    }
+
+    #[test]
+    fn allow_skips_tokens() {
+        let scope = |src: &str| {
+            let root = rnix::parse(src).node();
+            let comment = root
+                .descendants_with_tokens()
+                .filter_map(NodeOrToken::into_token)
+                .find(|t| t.kind() == SyntaxKind::TOKEN_COMMENT)
+                .unwrap();
+            let directive = Directive::parse(comment.text()).unwrap().unwrap();
+            directive.scope(&comment).map(|at| src[at].to_owned())
+        };
+        assert_eq!(
+            scope("{\n  # statix: allow(W03)\n  a = a;\n}"),
+            Some("a = a;".to_owned())
+        );
+        assert_eq!(scope("{\n  a = a;\n  # statix: allow(W03)\n}"), None);
+        assert_eq!(scope("[\n  a\n  # statix: allow(W03)\n]"), None);
+    }
+}
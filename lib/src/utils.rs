@@ -1,4 +1,9 @@
-use rnix::{SyntaxKind, SyntaxNode, TextRange};
+use crate::{make, Suggestion};
+
+use rnix::{
+    types::{LetIn, TypedNode},
+    SyntaxKind, SyntaxNode, TextRange,
+};
 
 pub fn with_preceeding_whitespace(node: &SyntaxNode) -> TextRange {
     let start = node
@@ -14,3 +19,20 @@ pub fn with_preceeding_whitespace(node: &SyntaxNode) -> TextRange {
     let end = node.text_range().end();
     TextRange::new(start, end)
 }
+
+/// Replace a let-in expression with its body, by deleting everything
+/// before the body. Bails out if the let-in contains comments, they
+/// would be lost otherwise.
+pub fn collapse_let_in(let_in: &LetIn) -> Option<Suggestion> {
+    let node = let_in.node();
+    let body = let_in.body()?;
+    let has_comments = node
+        .children_with_tokens()
+        .any(|el| el.kind() == SyntaxKind::TOKEN_COMMENT);
+    if has_comments {
+        None
+    } else {
+        let at = TextRange::new(node.text_range().start(), body.text_range().start());
+        Some(Suggestion::new(at, make::empty().node().clone()))
+    }
+}
@@ -1,31 +1,23 @@
 mod explain;
 mod metadata;
+mod options;
 
 use explain::generate_explain_impl;
 use metadata::{generate_meta_impl, RawLintMeta};
+use options::{extract_options, generate_options_fn, generate_self_impl};
 use proc_macro::TokenStream;
-use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{parse_macro_input, Ident, ItemStruct};
-
-fn generate_self_impl(struct_name: &Ident) -> TokenStream2 {
-    quote! {
-        impl #struct_name {
-            pub fn new() -> Box<Self> {
-                Box::new(Self)
-            }
-        }
-    }
-}
+use syn::{parse_macro_input, ItemStruct};
 
 #[proc_macro_attribute]
 pub fn lint(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let struct_item = parse_macro_input!(item as ItemStruct);
+    let mut struct_item = parse_macro_input!(item as ItemStruct);
     let meta = parse_macro_input!(attr as RawLintMeta);
+    let options = extract_options(&mut struct_item);
 
     let struct_name = &struct_item.ident;
-    let self_impl = generate_self_impl(struct_name);
-    let meta_impl = generate_meta_impl(struct_name, &meta);
+    let self_impl = generate_self_impl(struct_name, &options);
+    let meta_impl = generate_meta_impl(struct_name, &meta, generate_options_fn(&options));
     let explain_impl = generate_explain_impl(&struct_item);
 
     (quote! {
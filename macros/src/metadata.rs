@@ -41,7 +41,9 @@ pub struct LintMeta<'μ> {
     name: &'μ Lit,
     note: &'μ Lit,
     code: &'μ Lit,
+    category: &'μ Expr,
     match_with: MatchWith<'μ>,
+    applicability: Option<&'μ Path>,
 }
 
 enum MatchWith<'π> {
@@ -67,17 +69,24 @@ impl<'μ> LintMeta<'μ> {
         let name = as_lit(extract("name", raw));
         let note = as_lit(extract("note", raw));
         let code = as_lit(extract("code", raw));
+        let category = extract("category", raw);
         let match_with_expr = extract("match_with", raw);
         let match_with = match match_with_expr {
             Expr::Path(p) => MatchWith::Path(&p.path),
             Expr::Array(a) => MatchWith::Array(a),
             _ => panic!("`match_with` is neither a path nor an array"),
         };
+        let applicability = raw.0.get(&format_ident!("applicability")).map(|e| match e {
+            Expr::Path(p) => &p.path,
+            _ => panic!("`applicability` is not a path"),
+        });
         Self {
             name,
             note,
             code,
+            category,
             match_with,
+            applicability,
         }
     }
 
@@ -108,6 +117,15 @@ impl<'μ> LintMeta<'μ> {
         }
     }
 
+    fn generate_category_fn(&self) -> TokenStream2 {
+        let category = self.category;
+        quote! {
+            fn category(&self) -> crate::Category {
+                #category
+            }
+        }
+    }
+
     fn generate_match_with_fn(&self) -> TokenStream2 {
         match self.match_with {
             MatchWith::Path(p) => {
@@ -146,6 +164,19 @@ impl<'μ> LintMeta<'μ> {
         }
     }
 
+    // lints without an explicit applicability use the default of
+    // `Metadata::applicability`
+    fn generate_applicability_fn(&self) -> TokenStream2 {
+        match self.applicability {
+            Some(p) => quote! {
+                fn applicability(&self) -> crate::Applicability {
+                    #p
+                }
+            },
+            None => quote! {},
+        }
+    }
+
     fn generate_report_fn(&self) -> TokenStream2 {
         quote! {
             fn report(&self) -> crate::Report {
@@ -155,23 +186,32 @@ impl<'μ> LintMeta<'μ> {
     }
 }
 
-pub fn generate_meta_impl(struct_name: &Ident, meta: &RawLintMeta) -> TokenStream2 {
+pub fn generate_meta_impl(
+    struct_name: &Ident,
+    meta: &RawLintMeta,
+    options_fn: TokenStream2,
+) -> TokenStream2 {
     let not_raw = LintMeta::from_raw(meta);
     let name_fn = not_raw.generate_name_fn();
     let note_fn = not_raw.generate_note_fn();
     let code_fn = not_raw.generate_code_fn();
+    let category_fn = not_raw.generate_category_fn();
     let match_with_fn = not_raw.generate_match_with_fn();
     let match_kind = not_raw.generate_match_kind_fn();
     let report_fn = not_raw.generate_report_fn();
+    let applicability_fn = not_raw.generate_applicability_fn();
 
     quote! {
         impl crate::Metadata for #struct_name {
             #name_fn
             #note_fn
             #code_fn
+            #category_fn
             #match_with_fn
             #match_kind
             #report_fn
+            #applicability_fn
+            #options_fn
         }
     }
 }
@@ -0,0 +1,160 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse::ParseStream, Expr, Fields, Ident, ItemStruct, Lit, LitStr, Meta, MetaNameValue, Token,
+    Type,
+};
+
+/// A field of the lint marked with `#[option(default = ...)]`, or with
+/// `#[option(default = ..., values = ["a", "b"])]` to restrict the
+/// strings it may hold
+pub struct LintOption {
+    ident: Ident,
+    ty: Type,
+    default: Expr,
+    values: Vec<LitStr>,
+    doc: String,
+}
+
+fn parse_args(input: ParseStream) -> syn::Result<(Expr, Vec<LitStr>)> {
+    let key: Ident = input.parse()?;
+    if key != "default" {
+        return Err(syn::Error::new(key.span(), "expected `default = ...`"));
+    }
+    input.parse::<Token![=]>()?;
+    let default = input.parse()?;
+    if input.is_empty() {
+        return Ok((default, Vec::new()));
+    }
+    input.parse::<Token![,]>()?;
+    let key: Ident = input.parse()?;
+    if key != "values" {
+        return Err(syn::Error::new(key.span(), "expected `values = [...]`"));
+    }
+    input.parse::<Token![=]>()?;
+    let content;
+    syn::bracketed!(content in input);
+    let values = content.parse_terminated::<_, Token![,]>(|input| input.parse::<LitStr>())?;
+    Ok((default, values.into_iter().collect()))
+}
+
+fn doc_of(attrs: &[syn::Attribute]) -> String {
+    attrs
+        .iter()
+        .filter_map(|attr| match attr.parse_meta().ok() {
+            Some(Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Str(str_lit),
+                ..
+            })) if path.is_ident("doc") => Some(str_lit.value()),
+            _ => None,
+        })
+        .map(|s| s.trim().to_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Collect the options of the lint, and strip the `option` attributes
+/// off its fields
+pub fn extract_options(struct_item: &mut ItemStruct) -> Vec<LintOption> {
+    let fields = match &mut struct_item.fields {
+        Fields::Named(fields) => fields,
+        Fields::Unit => return Vec::new(),
+        Fields::Unnamed(_) => panic!("lints with options must have named fields"),
+    };
+    let options = fields
+        .named
+        .iter_mut()
+        .map(|field| {
+            let attr = field
+                .attrs
+                .iter()
+                .position(|attr| attr.path.is_ident("option"))
+                .map(|i| field.attrs.remove(i))
+                .unwrap_or_else(|| panic!("every field of a lint must be an `option`"));
+            let (default, values) = attr
+                .parse_args_with(parse_args)
+                .unwrap_or_else(|e| panic!("malformed `option`: {}", e));
+            LintOption {
+                ident: field.ident.clone().unwrap(),
+                ty: field.ty.clone(),
+                default,
+                values,
+                doc: doc_of(&field.attrs),
+            }
+        })
+        .collect();
+    options
+}
+
+pub fn generate_self_impl(struct_name: &Ident, options: &[LintOption]) -> TokenStream2 {
+    if options.is_empty() {
+        return quote! {
+            impl #struct_name {
+                pub fn new() -> Box<Self> {
+                    Box::new(Self)
+                }
+            }
+        };
+    }
+    let idents = options.iter().map(|o| &o.ident);
+    let defaults = options.iter().map(|o| &o.default);
+    quote! {
+        impl #struct_name {
+            pub fn new() -> Box<Self> {
+                Box::new(Self {
+                    #(#idents: #defaults),*
+                })
+            }
+
+            /// This lint, with the options set in `sess` in place of
+            /// the defaults, as resolved once for the session
+            pub fn configured<'a>(&'a self, sess: &'a crate::session::SessionInfo) -> &'a Self {
+                sess.configured(crate::Metadata::name(self))
+                    .and_then(|lint| lint.downcast_ref::<Self>())
+                    .unwrap_or(self)
+            }
+        }
+    }
+}
+
+pub fn generate_options_fn(options: &[LintOption]) -> TokenStream2 {
+    if options.is_empty() {
+        return quote! {};
+    }
+    let idents = options.iter().map(|o| &o.ident).collect::<Vec<_>>();
+    let names = idents.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+    let docs = options.iter().map(|o| &o.doc);
+    let tys = options.iter().map(|o| &o.ty).collect::<Vec<_>>();
+    let defaults = options.iter().map(|o| &o.default);
+    let values = options.iter().map(|o| &o.values);
+    quote! {
+        fn configure(
+            &self,
+            set: &::std::collections::BTreeMap<String, crate::OptionValue>,
+        ) -> Option<Box<dyn ::std::any::Any + Send + Sync>> {
+            Some(Box::new(Self {
+                #(#idents: set
+                    .get(#names)
+                    .and_then(<#tys as crate::OptionType>::from_value)
+                    .unwrap_or_else(|| self.#idents.clone())),*
+            }))
+        }
+
+        fn options(&self) -> Vec<crate::LintOption> {
+            vec![
+                #(crate::LintOption {
+                    name: #names,
+                    doc: #docs,
+                    ty: <#tys as crate::OptionType>::NAME,
+                    default: crate::OptionType::to_value(&{
+                        let default: #tys = #defaults;
+                        default
+                    }),
+                    accepts: |v| <#tys as crate::OptionType>::from_value(v).is_some(),
+                    values: &[#(#values),*],
+                }),*
+            ]
+        }
+    }
+}
@@ -67,14 +67,19 @@ impl ReadOnlyVfs {
         let file_id = self.alloc_file_id(path);
         self.data.insert(file_id, contents.to_owned());
     }
-    pub fn iter(&self) -> impl Iterator<Item = VfsEntry> {
+    /// Forget the contents of a file, its `FileId` stays allocated
+    pub fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> Option<Vec<u8>> {
+        let file_id = self.interner.get(path)?;
+        self.data.remove(&file_id)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = VfsEntry<'_>> {
         self.data.keys().map(move |file_id| VfsEntry {
             file_id: *file_id,
             file_path: self.file_path(*file_id),
             contents: self.get_str(*file_id),
         })
     }
-    pub fn par_iter(&self) -> impl ParallelIterator<Item = VfsEntry> {
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = VfsEntry<'_>> {
         self.data.par_iter().map(move |(file_id, _)| VfsEntry {
             file_id: *file_id,
             file_path: self.file_path(*file_id),
@@ -102,4 +107,19 @@ mod test {
         vfs.set_file_contents(f1, &data);
         assert_eq!(vfs.get(id1), &data);
     }
+
+    #[test]
+    fn remove() {
+        let mut vfs = ReadOnlyVfs::default();
+        let f1 = "a/b/c";
+        vfs.set_file_contents(f1, b"hello");
+        assert_eq!(vfs.remove_file(f1), Some(b"hello".to_vec()));
+        assert!(vfs.is_empty());
+        assert_eq!(vfs.remove_file(f1), None);
+
+        // the file id is reused once the file reappears
+        let id1 = vfs.alloc_file_id(f1);
+        vfs.set_file_contents(f1, b"world");
+        assert_eq!(vfs.get(id1), b"world");
+    }
 }